@@ -46,14 +46,19 @@ impl Client {
     }
 
     /// Check if the technique is allowed to run.
-    pub fn can_run(&self, info: ProjectInfo) {
+    ///
+    /// Returns the digest of the project's docker image (None if the server couldn't inspect it).
+    pub fn can_run(&self, info: ProjectInfo) -> Option<String> {
         serde_json::to_writer(&self.stream, &Msg::CanRun(info))
             .expect("Failed to send message to server");
         let mut de = serde_json::Deserializer::from_reader(&self.stream);
         let res = MsgResult::deserialize(&mut de).expect("Failed to receive response from server");
-        if let Err(err) = res {
-            eprintln!("{}", &err);
-            std::process::exit(1);
+        match res {
+            Ok(digest) => Some(digest).filter(|d| !d.is_empty()),
+            Err(err) => {
+                eprintln!("{}", &err);
+                std::process::exit(1);
+            }
         }
     }
 
@@ -147,6 +152,23 @@ impl Client {
             std::process::exit(1);
         }
     }
+
+    /// Generates the comparison reports for the given workspace uuid in its results page.
+    ///
+    /// Returns the name of the report files (without extension).
+    pub fn generate_report(&self, proj: ProjectInfo, uuid: &str) -> String {
+        serde_json::to_writer(&self.stream, &Msg::GenerateReport(proj, String::from(uuid)))
+            .expect("Failed to send message to server");
+        let mut de = serde_json::Deserializer::from_reader(&self.stream);
+        let res = MsgResult::deserialize(&mut de).expect("Failed to receive response from server");
+        match res {
+            Ok(name) => name,
+            Err(err) => {
+                eprintln!("{}", &err);
+                std::process::exit(1);
+            }
+        }
+    }
 }
 
 impl Drop for Client {
@@ -1,9 +1,16 @@
+use fbksd_core::registry::Build;
+use fbksd_core::runtime::{self, Outcome};
+use fbksd_core::system_config::BuildPolicy;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::process::Command;
+use std::str::FromStr;
 
 pub enum BuildType {
     Debug,
     Release,
+    RelWithDebInfo,
+    MinSizeRel,
 }
 impl Default for BuildType {
     fn default() -> Self {
@@ -16,28 +23,196 @@ impl fmt::Display for BuildType {
         match *self {
             Debug => "Debug".fmt(f),
             Release => "Release".fmt(f),
+            RelWithDebInfo => "RelWithDebInfo".fmt(f),
+            MinSizeRel => "MinSizeRel".fmt(f),
         }
     }
 }
+impl FromStr for BuildType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Debug" => Ok(BuildType::Debug),
+            "Release" => Ok(BuildType::Release),
+            "RelWithDebInfo" => Ok(BuildType::RelWithDebInfo),
+            "MinSizeRel" => Ok(BuildType::MinSizeRel),
+            _ => Err(()),
+        }
+    }
+}
+
+pub enum Generator {
+    Make,
+    Ninja,
+}
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Generator::Make => "Unix Makefiles".fmt(f),
+            Generator::Ninja => "Ninja".fmt(f),
+        }
+    }
+}
+impl FromStr for Generator {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Make" => Ok(Generator::Make),
+            "Ninja" => Ok(Generator::Ninja),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Build settings applied to `config()` and `install()`.
+pub struct Options {
+    pub build_type: BuildType,
+    /// cmake's default if None.
+    pub generator: Option<Generator>,
+    /// Number of parallel jobs (the generator's default if None).
+    pub jobs: Option<usize>,
+    pub definitions: BTreeMap<String, String>,
+    /// Build timeout, in seconds.
+    pub timeout: u64,
+}
+
+impl Options {
+    /// Returns the options from a technique's build settings.
+    ///
+    /// The settings are expected to be validated against the policy (see `info::validate()`), invalid values are
+    /// replaced by the defaults.
+    pub fn new(build: &Build, policy: &BuildPolicy) -> Options {
+        Options {
+            build_type: build
+                .build_type
+                .as_ref()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(BuildType::Release),
+            generator: build.generator.as_ref().and_then(|g| g.parse().ok()),
+            jobs: build.jobs.map(|jobs| jobs.min(policy.max_jobs)),
+            definitions: build
+                .definitions
+                .iter()
+                .filter(|(name, _)| policy.allows_definition(name))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            timeout: build.timeout.unwrap_or(policy.max_timeout).min(policy.max_timeout),
+        }
+    }
+}
+
+/// Returns the flags `config()` passes to `cmake` (without the source path).
+pub fn config_flags(options: &Options, install_prefix: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(generator) = &options.generator {
+        flags.push(String::from("-G"));
+        flags.push(generator.to_string());
+    }
+    flags.push(format!("-DCMAKE_BUILD_TYPE={}", options.build_type));
+    flags.push(format!("-DCMAKE_INSTALL_PREFIX={}", install_prefix));
+    for (name, value) in &options.definitions {
+        flags.push(format!("-D{}={}", name, value));
+    }
+    flags
+}
 
 /// Runs `cmake` with the given parameters on the current directory.
 pub fn config(
-    build_type: BuildType,
+    options: &Options,
     install_prefix: &str,
     source_path: &str,
 ) -> std::result::Result<std::process::ExitStatus, std::io::Error> {
     Command::new("cmake")
-        .args(&[
-            format!("-DCMAKE_BUILD_TYPE={}", build_type),
-            format!("-DCMAKE_INSTALL_PREFIX={}", install_prefix),
-            String::from(source_path),
-        ])
+        .args(config_flags(options, install_prefix))
+        .arg(source_path)
         .status()
 }
 
 /// Runs `cmake --build . --target install` on the current directory.
-pub fn install() -> std::result::Result<std::process::ExitStatus, std::io::Error> {
-    Command::new("cmake")
-        .args(&["--build", ".", "--target", "install"])
-        .status()
+///
+/// The build (with all its child processes) is killed if it doesn't finish before the options timeout.
+pub fn install(options: &Options) -> std::result::Result<Outcome, std::io::Error> {
+    let mut cmd = Command::new("cmake");
+    cmd.args(["--build", ".", "--target", "install"]);
+    if let Some(jobs) = options.jobs {
+        cmd.arg("--parallel").arg(jobs.to_string());
+    }
+    runtime::wait_timeout(runtime::spawn_group(&mut cmd)?, Some(options.timeout), runtime::kill_group)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> BuildPolicy {
+        BuildPolicy {
+            allowed_definitions: vec![String::from("CMAKE_CUDA_ARCHITECTURES"), String::from("MY_*")],
+            max_jobs: 4,
+            max_timeout: 600,
+        }
+    }
+
+    #[test]
+    fn test_options_new() {
+        let mut build = Build {
+            build_type: Some(String::from("Debug")),
+            generator: Some(String::from("Ninja")),
+            jobs: Some(16),
+            definitions: BTreeMap::new(),
+            timeout: Some(3600),
+        };
+        for name in &["CMAKE_CUDA_ARCHITECTURES", "MY_OPTION", "CMAKE_CXX_COMPILER", "MY"] {
+            build.definitions.insert(String::from(*name), String::from("1"));
+        }
+        let options = Options::new(&build, &policy());
+        assert!(matches!(options.build_type, BuildType::Debug));
+        assert!(matches!(options.generator, Some(Generator::Ninja)));
+        assert_eq!(options.jobs, Some(4));
+        assert_eq!(options.timeout, 600);
+        let names: Vec<&str> = options.definitions.keys().map(|n| n.as_str()).collect();
+        assert_eq!(names, ["CMAKE_CUDA_ARCHITECTURES", "MY_OPTION"]);
+
+        let build = Build {
+            build_type: Some(String::from("Fast")),
+            generator: Some(String::from("Xcode")),
+            jobs: Some(2),
+            definitions: BTreeMap::new(),
+            timeout: Some(60),
+        };
+        let options = Options::new(&build, &policy());
+        assert!(matches!(options.build_type, BuildType::Release));
+        assert!(options.generator.is_none());
+        assert_eq!(options.jobs, Some(2));
+        assert_eq!(options.timeout, 60);
+
+        let options = Options::new(&Build::default(), &policy());
+        assert!(options.jobs.is_none());
+        assert_eq!(options.timeout, 600);
+        assert!(options.definitions.is_empty());
+    }
+
+    #[test]
+    fn test_config_flags() {
+        let mut options = Options::new(&Build::default(), &policy());
+        assert_eq!(
+            config_flags(&options, "install"),
+            ["-DCMAKE_BUILD_TYPE=Release", "-DCMAKE_INSTALL_PREFIX=install"]
+        );
+
+        options.generator = Some(Generator::Make);
+        options.definitions.insert(String::from("MY_OPTION"), String::from("ON"));
+        assert_eq!(
+            config_flags(&options, "/tmp/install"),
+            [
+                "-G",
+                "Unix Makefiles",
+                "-DCMAKE_BUILD_TYPE=Release",
+                "-DCMAKE_INSTALL_PREFIX=/tmp/install",
+                "-DMY_OPTION=ON",
+            ]
+        );
+    }
+}
+
@@ -8,12 +8,16 @@ mod cmake;
 use client::Client;
 
 use fbksd_core;
-use fbksd_core::cd;
-use fbksd_core::ci::ProjectInfo;
+use fbksd_core::ci::{self, ProjectInfo};
 use fbksd_core::config;
 use fbksd_core::flock;
+use fbksd_core::info;
+use fbksd_core::manifest;
 use fbksd_core::paths;
 use fbksd_core::registry::Technique;
+use fbksd_core::runtime::{self, Outcome};
+use fbksd_core::suites;
+use fbksd_core::system_config::SystemConfig;
 use fbksd_core::utils::CD;
 
 use clap::{load_yaml, App};
@@ -21,7 +25,7 @@ use std::env;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::os::unix::fs as unixfs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 struct Paths {
@@ -64,32 +68,127 @@ fn verify_install() {
         eprintln!("Something was installed in the wrong place");
         std::process::exit(1);
     }
-    Technique::read(prefix.join("info.json")).expect("Failed to open info.json file");
+    let problems = info::validate_file(&prefix.join("info.json"), Some(&prefix));
+    if !problems.is_empty() {
+        eprintln!("Invalid installed info.json:");
+        for p in &problems {
+            eprintln!("  {}", p);
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Checks the CI config and the technique's info.json, reporting all problems found.
+///
+/// Returns false if any problem was found.
+fn check_project() -> bool {
+    let mut ok = true;
+    let provider = ci::detect();
+    if let Err(err) = ProjectInfo::load() {
+        eprintln!("CI ({}): {}", provider.name(), err);
+        ok = false;
+    }
+    let problems = info::validate_file(Path::new("info.json"), None);
+    for p in &problems {
+        eprintln!("info.json: {}", p);
+    }
+    ok && problems.is_empty()
 }
 
 fn validate_ci() {
-    ProjectInfo::load().unwrap();
+    if !check_project() {
+        std::process::exit(1);
+    }
+    println!("CI config and info.json are valid");
 }
 
 fn install() {
+    if !check_project() {
+        std::process::exit(1);
+    }
     register_current_technique();
 
+    let tech = Technique::read(PathBuf::from("info.json")).expect("Failed to read info.json");
+    let options = cmake::Options::new(&tech.build.unwrap_or_default(), &SystemConfig::load().build);
+
     fs::create_dir("build").expect("Failed to create build directory");
     let _cd = CD::new("build");
     // configure
-    let status = cmake::config(cmake::BuildType::Release, "install", "../")
-        .expect("Failed to execute cmake");
+    let status = cmake::config(&options, "install", "../").expect("Failed to execute cmake");
     if !status.success() {
         std::process::exit(1);
     }
 
     // build and install
-    let status = cmake::install().expect("Failed to execute cmake");
-    if !status.success() {
-        std::process::exit(1);
+    match cmake::install(&options).expect("Failed to execute cmake") {
+        Outcome::Exited(status) if status.success() => {}
+        Outcome::Exited(_) => std::process::exit(1),
+        Outcome::TimedOut => {
+            eprintln!("Build timed out after {} s", options.timeout);
+            std::process::exit(1);
+        }
     }
 
     verify_install();
+
+    // recorded in the workspace manifests
+    let flags = cmake::config_flags(&options, "install");
+    fs::write(
+        Path::new("install").join(paths::TECH_BUILD_FLAGS_FILE),
+        serde_json::to_string_pretty(&flags).unwrap(),
+    )
+    .expect("Failed to save build flags");
+}
+
+/// Runs the benchmark in the current workspace.
+///
+/// Each (scene, spp) runs separately, and its stdout and stderr are saved with the results (`<spp>_0_stdout.log`,
+/// `<spp>_0_stderr.log`), still being echoed to the console.
+/// A failed run doesn't stop the others: the metrics are computed for the successful ones, and the failures are
+/// returned, so the results (and logs) can still be saved before reporting them (see `exit_on_failures()`).
+fn run_benchmark() -> Vec<String> {
+    let max_size = SystemConfig::load().logs.max_size;
+    let workspace = Path::new(".");
+    let mut failures = Vec::new();
+    for unit in config::write_unit_configs(workspace) {
+        config::set_current(workspace, &unit.config);
+        let (status, output) = runtime::run_captured(Command::new("fbksd").arg("run"), max_size)
+            .expect("Failed to execute command");
+        for dir in &unit.results {
+            fs::create_dir_all(dir).expect("Failed to create results folder");
+            fs::write(dir.join(format!("{}_0_stdout.log", unit.spp)), &output.stdout)
+                .expect("Failed to save run log");
+            fs::write(dir.join(format!("{}_0_stderr.log", unit.spp)), &output.stderr)
+                .expect("Failed to save run log");
+        }
+        if !status.success() {
+            eprintln!("Run failed: scene {}, spp {}", unit.scene, unit.spp);
+            failures.push(format!("run of scene {}, spp {}", unit.scene, unit.spp));
+        }
+    }
+    config::set_current(workspace, config::MAIN_CONFIG);
+
+    // fbksd results compute
+    let status = Command::new("fbksd")
+        .args(&["results", "compute"])
+        .status()
+        .expect("Failed to execute command");
+    if !status.success() {
+        failures.push(String::from("metrics computation (fbksd results compute)"));
+    }
+    failures
+}
+
+/// Reports the failures returned by `run_benchmark()`, exiting with an error if there are any.
+fn exit_on_failures(failures: &[String]) {
+    if failures.is_empty() {
+        return;
+    }
+    eprintln!("{} benchmark step(s) failed:", failures.len());
+    for failure in failures {
+        eprintln!("  {}", failure);
+    }
+    std::process::exit(1);
 }
 
 fn run() {
@@ -98,7 +197,7 @@ fn run() {
         .expect("Failed to read info.json from project root dir");
 
     let client = Client::new();
-    client.can_run(proj.clone());
+    let digest = client.can_run(proj.clone());
 
     let paths = Paths::load();
 
@@ -109,6 +208,7 @@ fn run() {
     }
     fs::create_dir_all(&tmp_workspace).expect("Failed to create temporary workspace");
     unixfs::symlink(tmp_workspace, "workspace").expect("Failed to crate local workspace");
+    manifest::record_run_start(Path::new("workspace"), digest);
 
     // init and config new workspace
     {
@@ -134,31 +234,16 @@ fn run() {
     if !status.success() {
         std::process::exit(1);
     }
-    {
+    let failures = {
         let _cd = CD::new("workspace");
-        if config::fbksd_config().is_err() {
-            std::process::exit(1);
-        }
-
-        // fbksd run
-        let status = Command::new("fbksd")
-            .arg("run")
-            .status()
-            .expect("Failed to execute command");
-        if !status.success() {
+        let suite = proj.suite.as_ref().and_then(|id| suites::find(id));
+        if config::fbksd_config(suite.as_ref()).is_err() {
             std::process::exit(1);
         }
+        run_benchmark()
+    };
 
-        // fbksd results compute
-        let status = Command::new("fbksd")
-            .args(&["results", "compute"])
-            .status()
-            .expect("Failed to execute command");
-        if !status.success() {
-            std::process::exit(1);
-        }
-    }
-
+    // the results of failed runs are saved too, so their logs can be checked in the private page
     let uuid = client.save_results(proj.clone(), tech);
     client.publish_results_private(proj, &uuid);
 
@@ -167,6 +252,7 @@ fn run() {
     println!("{}", "*".repeat(link.len()));
     println!("{}", link);
     println!("{}", "*".repeat(link.len()));
+    exit_on_failures(&failures);
 }
 
 fn publish() {
@@ -180,25 +266,13 @@ fn publish() {
         .init_missing_scenes_workspace(proj.clone(), &uuid)
         .is_some()
     {
-        cd!(paths.tmp_workspace, {
-            // fbksd run
-            let status = Command::new("fbksd")
-                .arg("run")
-                .status()
-                .expect("Failed to execute command");
-            if !status.success() {
-                std::process::exit(1);
-            }
-            // fbksd results compute
-            let status = Command::new("fbksd")
-                .args(&["results", "compute"])
-                .status()
-                .expect("Failed to execute command");
-            if !status.success() {
-                std::process::exit(1);
-            }
+        let failures = {
+            let _cd = CD::new(&paths.tmp_workspace);
+            let failures = run_benchmark();
             client.update_results(proj.clone(), &uuid);
-        });
+            failures
+        };
+        exit_on_failures(&failures);
     }
     client.publish_results_public(proj, &uuid);
     let link = "https://fbksd.inf.ufrgs.br/results/";
@@ -218,6 +292,22 @@ fn delete_workspace() {
     println!("Workspace deleted");
 }
 
+fn report() {
+    let proj = ProjectInfo::load().unwrap();
+    const FBKSD_REPORT: &str = "FBKSD_REPORT";
+    let uuid = env::var(FBKSD_REPORT).expect(&format!("Evn var {} not defined", FBKSD_REPORT));
+    let name = Client::new().generate_report(proj, &uuid);
+    let base = format!("https://fbksd.inf.ufrgs.br/results/{}/{}", &uuid, &name);
+    let links = vec![format!("{}.html", &base), format!("{}.md", &base)];
+    let width = links.iter().map(|l| l.len()).max().unwrap();
+    println!("Report Links:");
+    println!("{}", "*".repeat(width));
+    for link in &links {
+        println!("{}", link);
+    }
+    println!("{}", "*".repeat(width));
+}
+
 fn main() {
     let yaml = load_yaml!("cli.yml");
     let matches = App::from_yaml(yaml).get_matches();
@@ -228,6 +318,7 @@ fn main() {
         Some("run") => flock! { run() },
         Some("publish") => flock! { publish() },
         Some("delete-workspace") => flock! { delete_workspace() },
+        Some("report") => flock! { report() },
         None => println!("No subcommand was used"),
         _ => unreachable!(),
     }
@@ -1,3 +1,4 @@
+use crate::suites;
 use crate::system_config::SystemConfig;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
@@ -13,6 +14,8 @@ pub enum CIError {
     CIConfigMissingInclude,
     CIConfigImageNotFound,
     BadCIConfig,
+    SuiteNotFound,
+    InvalidProjectId,
     Unspecified,
 }
 impl fmt::Display for CIError {
@@ -20,20 +23,26 @@ impl fmt::Display for CIError {
         use CIError::*;
         match *self {
             MissingEnvVar => "missing CI environment variable".fmt(f),
-            CIConfigNotFound => "\".gitlab-ci.yml\" file not found".fmt(f),
+            CIConfigNotFound => "CI pipeline config file not found".fmt(f),
             CIConfigMissingInclude => {
-                "\".gitlab-ci.yml\" file missing correct \"include\" statement".fmt(f)
+                "CI pipeline config missing the \"fbksd/fbksd_ci_config\" include (at \"master\")".fmt(f)
             }
-            CIConfigImageNotFound => "config in \".gitlab-ci.yml\" was not found".fmt(f),
-            BadCIConfig => "bad \".gitlab-ci.yml\" format".fmt(f),
+            CIConfigImageNotFound => "config in the CI pipeline config was not found".fmt(f),
+            BadCIConfig => "bad CI pipeline config format".fmt(f),
+            SuiteNotFound => "benchmark suite not found".fmt(f),
+            InvalidProjectId => "invalid project id (only letters, digits, \"_\" and \".\" are allowed)".fmt(f),
             Unspecified => "unspecified error".fmt(f),
         }
     }
 }
 impl error::Error for CIError {}
-type CIConfigResult = Result<CIConfig, CIError>;
 pub type ProjectInfoResult = Result<ProjectInfo, CIError>;
 
+/// Project that holds the fbksd CI configs.
+static CI_CONFIG_PROJECT: &str = "fbksd/fbksd_ci_config";
+/// Branch of `CI_CONFIG_PROJECT` the pipelines must use.
+static CI_CONFIG_REF: &str = "master";
+
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
 struct CIProjInclude {
@@ -41,8 +50,6 @@ struct CIProjInclude {
     #[serde(rename = "ref")]
     git_ref: String,
     file: String,
-    #[serde(skip)]
-    docker_img: String,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
@@ -52,50 +59,214 @@ struct CIConfig {
     includes: Vec<CIProjInclude>,
 }
 
-impl CIConfig {
-    // fn new() -> CIConfig {
-    //     CIConfig {
-    //         includes: vec![CIProjInclude {
-    //             project: String::from("fbksd/fbksd_ci_config"),
-    //             git_ref: String::from("master"),
-    //             file: String::from("/config.yml"),
-    //         }],
-    //     }
-    // }
-
-    fn load() -> CIConfigResult {
-        let data = fs::read_to_string(".gitlab-ci.yml");
-        if data.is_err() {
-            return Err(CIError::CIConfigNotFound);
+/// Returns the fbksd CI config used by a `.gitlab-ci.yml` file.
+fn gitlab_config_name(data: &str) -> Result<String, CIError> {
+    let config: CIConfig = match serde_yaml::from_str(data) {
+        Ok(config) => config,
+        Err(_) => return Err(CIError::Unspecified),
+    };
+    // only one include allowed
+    if config.includes.len() != 1 {
+        return Err(CIError::BadCIConfig);
+    }
+    // the include should have the right project and branch
+    let inc = &config.includes[0];
+    if inc.project != CI_CONFIG_PROJECT || inc.git_ref != CI_CONFIG_REF {
+        return Err(CIError::CIConfigMissingInclude);
+    }
+    // "/<config>.yml"
+    if !inc.file.starts_with('/') || !inc.file.ends_with(".yml") {
+        return Err(CIError::BadCIConfig);
+    }
+    Ok(String::from(&inc.file[1..inc.file.len() - 4]))
+}
+
+/// Returns the fbksd CI config used by a GitHub (or Gitea) Actions workflow file.
+///
+/// The workflow must have exactly one job calling a `CI_CONFIG_PROJECT` reusable workflow
+/// (`uses: fbksd/fbksd_ci_config/.github/workflows/<config>.yml@master`).
+fn workflow_config_name(data: &str) -> Result<String, CIError> {
+    let workflow: serde_yaml::Value = match serde_yaml::from_str(data) {
+        Ok(workflow) => workflow,
+        Err(_) => return Err(CIError::Unspecified),
+    };
+    let jobs = match workflow.get("jobs").and_then(|jobs| jobs.as_mapping()) {
+        Some(jobs) => jobs,
+        None => return Err(CIError::BadCIConfig),
+    };
+    let prefix = format!("{}/.github/workflows/", CI_CONFIG_PROJECT);
+    let uses: Vec<&str> = jobs
+        .iter()
+        .filter_map(|(_, job)| job.get("uses").and_then(|uses| uses.as_str()))
+        .filter(|uses| uses.starts_with(&prefix))
+        .collect();
+    if uses.len() != 1 {
+        return Err(CIError::CIConfigMissingInclude);
+    }
+    let (file, git_ref) = match uses[0][prefix.len()..].split_once('@') {
+        Some(parts) => parts,
+        None => return Err(CIError::CIConfigMissingInclude),
+    };
+    if git_ref != CI_CONFIG_REF {
+        return Err(CIError::CIConfigMissingInclude);
+    }
+    match file.strip_suffix(".yml") {
+        Some(name) if !name.is_empty() && !name.contains('/') => Ok(String::from(name)),
+        _ => Err(CIError::BadCIConfig),
+    }
+}
+
+fn env_var(name: &str) -> Result<String, CIError> {
+    env::var(name).map_err(|_| CIError::MissingEnvVar)
+}
+
+/// Returns true if `id` is a valid provider id: only ASCII letters, digits, `_` and `.`, and not only dots.
+fn is_valid_id(id: &str) -> bool {
+    id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') && !id.chars().all(|c| c == '.')
+}
+
+/// Returns true if `id` is a valid registry id of a project (see `project_id()`).
+///
+/// Ids are used in paths, so the server must check the ids it receives.
+pub fn is_valid_project_id(id: &str) -> bool {
+    id.split('-').all(is_valid_id)
+}
+
+/// Returns the registry id of a project from its provider id, prefixed by the provider namespace.
+///
+/// Ids are used in paths (mounted in docker containers), so `-` separates the namespace parts instead of `:`.
+fn project_id(namespace: &[&str], id: &str) -> Result<String, CIError> {
+    if !is_valid_id(id) {
+        return Err(CIError::InvalidProjectId);
+    }
+    let mut parts: Vec<String> = namespace
+        .iter()
+        .map(|part| {
+            part.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+                .collect()
+        })
+        .collect();
+    parts.push(String::from(id));
+    Ok(parts.join("-"))
+}
+
+/// Returns the host (and port) of a server url.
+fn server_host(url: &str) -> &str {
+    let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+    host.trim_end_matches('/')
+}
+
+/// CI system running a technique pipeline.
+///
+/// Providers map their environment to the project info, and validate their pipeline config.
+/// Project ids from all providers share the same registry, so they must not collide: only GitLab ids are kept as
+/// they are, the others are namespaced (see `project_id()`).
+pub trait Provider {
+    fn name(&self) -> &'static str;
+
+    /// Returns the project id and the (short) commit sha.
+    fn project(&self) -> Result<(String, String), CIError>;
+
+    /// Validates the pipeline config, returning the fbksd CI config (docker image alias) it uses.
+    fn config_name(&self) -> Result<String, CIError>;
+}
+
+/// GitLab CI (`.gitlab-ci.yml` including `CI_CONFIG_PROJECT`).
+pub struct GitLab;
+
+impl Provider for GitLab {
+    fn name(&self) -> &'static str {
+        "gitlab"
+    }
+
+    fn project(&self) -> Result<(String, String), CIError> {
+        let id = env_var("CI_PROJECT_ID")?;
+        if !is_valid_id(&id) {
+            return Err(CIError::InvalidProjectId);
         }
-        match serde_yaml::from_str::<CIConfig>(&data.unwrap()) {
-            Ok(mut config) => {
-                // only one include allowed
-                if config.includes.len() != 1 {
-                    return Err(CIError::BadCIConfig);
-                }
+        Ok((id, env_var("CI_COMMIT_SHORT_SHA")?))
+    }
 
-                // the include should have the write project and branch
-                let mut inc = &mut config.includes[0];
-                if inc.project != "fbksd/fbksd_ci_config" || inc.git_ref != "master" {
-                    return Err(CIError::CIConfigMissingInclude);
-                }
+    fn config_name(&self) -> Result<String, CIError> {
+        match fs::read_to_string(".gitlab-ci.yml") {
+            Ok(data) => gitlab_config_name(&data),
+            Err(_) => Err(CIError::CIConfigNotFound),
+        }
+    }
+}
 
-                let sys_config = SystemConfig::load();
-                match sys_config.configs.get(&inc.file[1..inc.file.len() - 4]) {
-                    Some(img) => {
-                        inc.docker_img = img.clone();
-                        return Ok(config);
-                    }
-                    None => Err(CIError::CIConfigImageNotFound),
-                }
-            }
-            Err(_) => Err(CIError::Unspecified),
+/// GitHub Actions, or Gitea Actions (which is compatible with it).
+pub struct Actions {
+    /// Gitea instead of GitHub.
+    pub gitea: bool,
+}
+
+impl Provider for Actions {
+    fn name(&self) -> &'static str {
+        if self.gitea {
+            "gitea"
+        } else {
+            "github"
+        }
+    }
+
+    fn project(&self) -> Result<(String, String), CIError> {
+        let id = env_var("GITHUB_REPOSITORY_ID")?;
+        // each gitea instance has its own repository ids
+        let id = if self.gitea {
+            project_id(&["gitea", server_host(&env_var("GITHUB_SERVER_URL")?)], &id)?
+        } else {
+            project_id(&["github"], &id)?
+        };
+        let mut commit_sha = env_var("GITHUB_SHA")?;
+        // same length as gitlab's short sha
+        commit_sha.truncate(8);
+        Ok((id, commit_sha))
+    }
+
+    fn config_name(&self) -> Result<String, CIError> {
+        let path = if self.gitea {
+            ".gitea/workflows/fbksd.yml"
+        } else {
+            ".github/workflows/fbksd.yml"
+        };
+        match fs::read_to_string(path) {
+            Ok(data) => workflow_config_name(&data),
+            Err(_) => Err(CIError::CIConfigNotFound),
         }
     }
+}
+
+/// Any other CI system, configured only with environment variables (`FBKSD_PROJECT_ID`, `FBKSD_COMMIT_SHA` and
+/// `FBKSD_CI_CONFIG`).
+pub struct Env;
+
+impl Provider for Env {
+    fn name(&self) -> &'static str {
+        "env"
+    }
 
-    pub fn docker_img(&self) -> &str {
-        &self.includes[0].docker_img
+    fn project(&self) -> Result<(String, String), CIError> {
+        Ok((project_id(&["env"], &env_var("FBKSD_PROJECT_ID")?)?, env_var("FBKSD_COMMIT_SHA")?))
+    }
+
+    fn config_name(&self) -> Result<String, CIError> {
+        env_var("FBKSD_CI_CONFIG")
+    }
+}
+
+/// Detects the CI system from the environment (falling back to `Env`).
+pub fn detect() -> Box<dyn Provider> {
+    let is_set = |name| env::var(name).map_or(false, |v| v == "true");
+    if is_set("GITLAB_CI") {
+        Box::new(GitLab)
+    } else if is_set("GITEA_ACTIONS") {
+        Box::new(Actions { gitea: true })
+    } else if is_set("GITHUB_ACTIONS") {
+        Box::new(Actions { gitea: false })
+    } else {
+        Box::new(Env)
     }
 }
 
@@ -104,29 +275,88 @@ pub struct ProjectInfo {
     pub id: String,
     pub commit_sha: String,
     pub docker_img: String,
+    /// Benchmark suite the technique runs with (all scenes if None).
+    #[serde(default)]
+    pub suite: Option<String>,
 }
 
 impl ProjectInfo {
+    /// Loads the project info from the detected CI system (see `detect()`).
     pub fn load() -> ProjectInfoResult {
-        const CI_PROJECT_ID: &str = "CI_PROJECT_ID";
-        let id = env::var(CI_PROJECT_ID);
-        if id.is_err() {
-            return Err(CIError::CIConfigNotFound);
-        }
-        let id = id.unwrap();
+        let provider = detect();
+        let (id, commit_sha) = provider.project()?;
 
-        const CI_COMMIT_SHORT_SHA: &str = "CI_COMMIT_SHORT_SHA";
-        let commit_sha = env::var(CI_COMMIT_SHORT_SHA);
-        if commit_sha.is_err() {
-            return Err(CIError::CIConfigNotFound);
-        }
-        let commit_sha = commit_sha.unwrap();
+        // optional suite
+        const FBKSD_SUITE: &str = "FBKSD_SUITE";
+        let suite = match env::var(FBKSD_SUITE) {
+            Ok(suite) => {
+                if suites::find(&suite).is_none() {
+                    return Err(CIError::SuiteNotFound);
+                }
+                Some(suite)
+            }
+            Err(_) => None,
+        };
 
-        let ci_config = CIConfig::load()?;
+        let config_name = provider.config_name()?;
+        let docker_img = match SystemConfig::load().configs.get(&config_name) {
+            Some(img) => img.clone(),
+            None => return Err(CIError::CIConfigImageNotFound),
+        };
         Ok(ProjectInfo {
             id,
             commit_sha,
-            docker_img: String::from(ci_config.docker_img()),
+            docker_img,
+            suite,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitlab_config_name() {
+        let data = "include:\n  - project: fbksd/fbksd_ci_config\n    ref: master\n    file: /cuda.yml\n";
+        assert_eq!(gitlab_config_name(data).unwrap(), "cuda");
+        let data = data.replace("master", "dev");
+        assert!(gitlab_config_name(&data).is_err());
+    }
+
+    #[test]
+    fn test_workflow_config_name() {
+        let data = "on: push\njobs:\n  fbksd:\n    uses: fbksd/fbksd_ci_config/.github/workflows/cuda.yml@master\n    secrets: inherit\n";
+        assert_eq!(workflow_config_name(data).unwrap(), "cuda");
+        assert!(workflow_config_name(&data.replace("@master", "@v1")).is_err());
+        assert!(workflow_config_name("on: push\njobs:\n  build:\n    runs-on: ubuntu-latest\n").is_err());
+    }
+
+    #[test]
+    fn test_project_id() {
+        let ids = [
+            String::from("42"),
+            project_id(&["github"], "42").unwrap(),
+            project_id(&["gitea", server_host("https://gitea.example.com/")], "42").unwrap(),
+            project_id(&["gitea", server_host("http://gitea.example.com:3000")], "42").unwrap(),
+            project_id(&["env"], "42").unwrap(),
+            project_id(&["env"], "github_42").unwrap(),
+        ];
+        assert_eq!(ids[2], "gitea-gitea.example.com-42");
+        let unique: std::collections::HashSet<&String> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        for id in &ids[1..] {
+            assert!(id.parse::<i32>().is_err());
+            assert!(!id.contains(':') && !id.contains('/'));
+            assert!(is_valid_project_id(id));
+        }
+
+        for id in &["", ".", "..", "../../etc", "a/b", "github-42", "a b", "x\0"] {
+            assert!(project_id(&["env"], id).is_err(), "{:?} accepted", id);
+        }
+        for id in &["", ".", "..", "../x", "a/b", "env-..", "env-"] {
+            assert!(!is_valid_project_id(id), "{:?} accepted", id);
+        }
+        assert!(is_valid_project_id("env-my_project.v2"));
+    }
+}
@@ -3,17 +3,18 @@
 //! fbksd configurations describe how a benchmark should be executed: what scenes, techniques, and spps.
 
 use crate::paths;
+use crate::suites::Suite;
 use crate::utils;
 use crate::workspace as wp;
-use crate::system_config::SystemConfig;
+use crate::system_config::{ShardBy, SystemConfig};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::os::unix::fs as unixfs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct Technique {
     name: String,
     versions: Vec<String>,
@@ -28,22 +29,22 @@ impl Technique {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct Scene {
     name: String,
     spps: Vec<i32>,
 }
 
 impl Scene {
-    fn new(name: &str) -> Self {
+    fn new(name: &str, spps: &[i32]) -> Self {
         Scene {
             name: name.to_string(),
-            spps: vec![],
+            spps: Vec::from(spps),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct Renderer {
     name: String,
     scenes: Vec<Scene>,
@@ -58,7 +59,7 @@ impl Renderer {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct Config {
     renderers: Vec<Renderer>,
     filters: Vec<Technique>,
@@ -74,6 +75,11 @@ impl Config {
         }
     }
 
+    fn load(file: &Path) -> Config {
+        let data = fs::read_to_string(file).expect("Error reading config.");
+        serde_json::from_str(&data).expect("Error deserializing config.")
+    }
+
     fn save(&self, file: &Path) {
         let data = serde_json::to_string_pretty(self).expect("Error serializing config.");
         fs::write(file, &data).expect("Error saving config.");
@@ -88,25 +94,279 @@ impl Config {
         }
     }
 
-    fn set_spps(&mut self, spps: &[i32]) {
+    /// Removes the given scenes (and renderers left without scenes).
+    fn remove_scenes(&mut self, scenes: &HashSet<String>) {
+        for r in &mut self.renderers {
+            r.scenes.retain(|s| !scenes.contains(&s.name));
+        }
+        self.renderers.retain(|r| !r.scenes.is_empty());
+    }
+
+    /// Sets the spps of each scene (scene name -> spps). Scenes not in `spps` are not changed.
+    fn set_spps(&mut self, spps: &HashMap<String, Vec<i32>>) {
         for r in &mut self.renderers {
             for s in &mut r.scenes {
-                s.spps = Vec::from(spps);
+                if let Some(spps) = spps.get(&s.name) {
+                    s.spps = spps.clone();
+                }
             }
         }
     }
 }
 
-/// Generates a config for the given technique and scenes.
+/// Name of the config with all the work of a workspace (the other configs are subsets of it).
+pub static MAIN_CONFIG: &str = "all.json";
+
+/// Sets the current config of a workspace (`configs/.current.json` link).
+pub fn set_current(workspace: &Path, name: &str) {
+    let link = workspace.join("configs/.current.json");
+    if fs::symlink_metadata(&link).is_ok() {
+        fs::remove_file(&link).expect("Failed to remove current config link");
+    }
+    unixfs::symlink(name, &link).expect("Failed to link current config");
+}
+
+/// Writes configs with the results of the main config of a workspace that have no log yet.
 ///
-/// This does not uses the `fbksd` script.
-/// Binaries, and results are not copied.
-pub fn gen_config<'a, I, J, K>(path: &Path, denoisers: I, samplers: I, scenes: J)
-where
-    I: IntoIterator<Item = &'a K>,
-    J: IntoIterator<Item = &'a K>,
-    K: AsRef<str> + 'a,
-{
+/// Techniques missing the same (scene, spps) results share a config, so each result without log is in exactly one
+/// config. Returns the names of the configs written (`resume-<i>.json`).
+pub fn write_resume_configs(workspace: &Path) -> Vec<String> {
+    let config = Config::load(&workspace.join("configs").join(MAIN_CONFIG));
+    let has_log = |group: &str, tech: &Technique, scene: &str, spp: i32| {
+        tech.versions.iter().all(|version| {
+            workspace
+                .join("results/.current")
+                .join(group)
+                .join(&tech.name)
+                .join(version)
+                .join(scene)
+                .join(format!("{}_0_log.json", spp))
+                .exists()
+        })
+    };
+
+    // missing renderers/scenes/spps -> techniques
+    let mut groups: Vec<(Vec<Renderer>, Config)> = Vec::new();
+    let techniques = config
+        .filters
+        .iter()
+        .map(|t| ("denoisers", t))
+        .chain(config.samplers.iter().map(|t| ("samplers", t)));
+    for (group, tech) in techniques {
+        let missing: Vec<Renderer> = config
+            .renderers
+            .iter()
+            .map(|r| {
+                let scenes = r
+                    .scenes
+                    .iter()
+                    .map(|s| {
+                        let spps: Vec<i32> = s
+                            .spps
+                            .iter()
+                            .filter(|spp| !has_log(group, tech, &s.name, **spp))
+                            .cloned()
+                            .collect();
+                        Scene::new(&s.name, &spps)
+                    })
+                    .filter(|s| !s.spps.is_empty())
+                    .collect();
+                Renderer::new(&r.name, scenes)
+            })
+            .filter(|r| !r.scenes.is_empty())
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let key = serde_json::to_string(&missing).unwrap();
+        let index = match groups
+            .iter()
+            .position(|(renderers, _)| serde_json::to_string(renderers).unwrap() == key)
+        {
+            Some(index) => index,
+            None => {
+                let mut c = Config::new();
+                c.renderers = missing.clone();
+                groups.push((missing, c));
+                groups.len() - 1
+            }
+        };
+        let c = &mut groups[index].1;
+        match group {
+            "denoisers" => c.filters.push(tech.clone()),
+            _ => c.samplers.push(tech.clone()),
+        }
+    }
+
+    let mut names = Vec::new();
+    for (i, (_, c)) in groups.iter().enumerate() {
+        let name = format!("resume-{}.json", i);
+        c.save(&workspace.join("configs").join(&name));
+        names.push(name);
+    }
+    names
+}
+
+/// Returns the log files of all results expected from the current config of a workspace, with their spps.
+///
+/// Paths are relative to the workspace (`results/.current/<group>/<technique>/<version>/<scene>/<spp>_0_log.json`).
+pub fn expected_logs(workspace: &Path) -> Vec<(PathBuf, i32)> {
+    let config = Config::load(&workspace.join("configs/.current.json"));
+    let techniques = config
+        .filters
+        .iter()
+        .map(|t| ("denoisers", t))
+        .chain(config.samplers.iter().map(|t| ("samplers", t)));
+    let mut logs = Vec::new();
+    for (group, tech) in techniques {
+        for version in &tech.versions {
+            for scene in config.renderers.iter().flat_map(|r| &r.scenes) {
+                for spp in &scene.spps {
+                    let log = PathBuf::from("results/.current")
+                        .join(group)
+                        .join(&tech.name)
+                        .join(version)
+                        .join(&scene.name)
+                        .join(format!("{}_0_log.json", spp));
+                    logs.push((log, *spp));
+                }
+            }
+        }
+    }
+    logs
+}
+
+/// A single (scene, spp) of the current config of a workspace, with all its techniques.
+#[derive(Debug)]
+pub struct RunUnit {
+    /// Name of the unit config (see `write_unit_configs()`).
+    pub config: String,
+    pub scene: String,
+    pub spp: i32,
+    /// Results folders of the scene (one per technique version), relative to the workspace.
+    pub results: Vec<PathBuf>,
+}
+
+/// Splits the current config of a workspace into one config per (scene, spp), saved as `unit-<i>.json`.
+///
+/// Used to run each scene and spp separately, e.g. to capture the output of each run.
+pub fn write_unit_configs(workspace: &Path) -> Vec<RunUnit> {
+    let config = Config::load(&workspace.join("configs/.current.json"));
+    let techniques: Vec<(&str, &Technique)> = config
+        .filters
+        .iter()
+        .map(|t| ("denoisers", t))
+        .chain(config.samplers.iter().map(|t| ("samplers", t)))
+        .collect();
+    let mut units = Vec::new();
+    for r in &config.renderers {
+        for scene in &r.scenes {
+            for spp in &scene.spps {
+                let mut c = config.clone();
+                c.renderers = vec![Renderer::new(&r.name, vec![Scene::new(&scene.name, &[*spp])])];
+                let name = format!("unit-{}.json", units.len());
+                c.save(&workspace.join("configs").join(&name));
+                let results = techniques
+                    .iter()
+                    .flat_map(|(group, tech)| {
+                        tech.versions.iter().map(move |version| {
+                            PathBuf::from("results/.current")
+                                .join(group)
+                                .join(&tech.name)
+                                .join(version)
+                                .join(&scene.name)
+                        })
+                    })
+                    .collect();
+                units.push(RunUnit {
+                    config: name,
+                    scene: scene.name.clone(),
+                    spp: *spp,
+                    results,
+                });
+            }
+        }
+    }
+    units
+}
+
+/// Returns the number of units (scenes or techniques) the current config of a workspace can be split into.
+pub fn shard_units(workspace: &Path, by: ShardBy) -> usize {
+    let config = Config::load(&workspace.join("configs/.current.json"));
+    match by {
+        ShardBy::Scene => config.renderers.iter().map(|r| r.scenes.len()).sum(),
+        ShardBy::Technique => config.filters.len() + config.samplers.len(),
+    }
+}
+
+/// Splits the current config of a workspace between the given shard workspaces.
+///
+/// Scenes (or techniques) are distributed round-robin, and each shard config is saved in the same file as the
+/// workspace's current config (replacing the file, not its content).
+pub fn write_config_shards(workspace: &Path, shards: &[PathBuf], by: ShardBy) {
+    let current = workspace.join("configs/.current.json");
+    let config_file = match fs::read_link(&current) {
+        Ok(target) => Path::new("configs").join(target),
+        Err(_) => PathBuf::from("configs/.current.json"),
+    };
+    let config = Config::load(&current);
+    let mut configs: Vec<Config> = shards
+        .iter()
+        .map(|_| {
+            let mut c = config.clone();
+            match by {
+                ShardBy::Scene => c.renderers.clear(),
+                ShardBy::Technique => {
+                    c.filters.clear();
+                    c.samplers.clear();
+                }
+            }
+            c
+        })
+        .collect();
+    match by {
+        ShardBy::Scene => {
+            let scenes = config
+                .renderers
+                .iter()
+                .flat_map(|r| r.scenes.iter().map(move |s| (&r.name, s)));
+            for (i, (renderer, scene)) in scenes.enumerate() {
+                let c = &mut configs[i % shards.len()];
+                match c.renderers.iter_mut().find(|r| &r.name == renderer) {
+                    Some(r) => r.scenes.push(scene.clone()),
+                    None => c.renderers.push(Renderer::new(renderer, vec![scene.clone()])),
+                }
+            }
+        }
+        ShardBy::Technique => {
+            let techs = config
+                .filters
+                .iter()
+                .map(|t| (true, t))
+                .chain(config.samplers.iter().map(|t| (false, t)));
+            for (i, (is_filter, tech)) in techs.enumerate() {
+                let c = &mut configs[i % shards.len()];
+                if is_filter {
+                    c.filters.push(tech.clone());
+                } else {
+                    c.samplers.push(tech.clone());
+                }
+            }
+        }
+    }
+    for (shard, c) in shards.iter().zip(&configs) {
+        // The shard file may be hard-linked to the workspace's (see `workspace::create_tmp_shards()`): unlink it
+        // instead of overwriting the shared content.
+        let path = shard.join(&config_file);
+        if path.exists() {
+            fs::remove_file(&path).expect("Failed to remove shard config");
+        }
+        c.save(&path);
+    }
+}
+
+/// Creates the folders and links of a workspace (without the `fbksd` script).
+fn init_workspace(path: &Path) {
     unixfs::symlink(
         utils::relative_from(paths::scenes_path(), path).unwrap(),
         path.join("scenes"),
@@ -127,15 +387,89 @@ where
     fs::create_dir(path.join("configs")).expect("failed to create configs dir");
     fs::create_dir_all(path.join("results/Results 1")).expect("failed to create results dir");
     unixfs::symlink("Results 1", path.join("results/.current")).expect("failed to link iqa folder");
+}
+
+/// Returns the main config of a workspace with only the given technique (`group` is `denoisers` or `samplers`).
+pub fn technique_config(workspace: &Path, group: &str, name: &str) -> serde_json::Value {
+    let mut config = Config::load(&workspace.join("configs").join(MAIN_CONFIG));
+    config.filters.retain(|t| group == "denoisers" && t.name == name);
+    config.samplers.retain(|t| group == "samplers" && t.name == name);
+    serde_json::to_value(&config).expect("Error serializing config.")
+}
+
+/// Returns the names of the scenes in a config (see `technique_config()`).
+pub fn config_scenes(config: &serde_json::Value) -> Vec<String> {
+    let config: Config = serde_json::from_value(config.clone()).expect("Error deserializing config.");
+    config
+        .renderers
+        .iter()
+        .flat_map(|r| r.scenes.iter().map(|s| s.name.clone()))
+        .collect()
+}
+
+/// Returns the `update` config with the scenes (and spps) of the `base` config it doesn't include.
+///
+/// Used to combine the configs of a technique's saves (see `technique_config()`), where later saves may only run
+/// some of its scenes.
+pub fn merge_configs(base: &serde_json::Value, update: &serde_json::Value) -> serde_json::Value {
+    let base: Config = serde_json::from_value(base.clone()).expect("Error deserializing config.");
+    let mut config: Config = serde_json::from_value(update.clone()).expect("Error deserializing config.");
+    for r in base.renderers {
+        let renderer = match config.renderers.iter_mut().position(|cr| cr.name == r.name) {
+            Some(i) => &mut config.renderers[i],
+            None => {
+                config.renderers.push(Renderer::new(&r.name, Vec::new()));
+                config.renderers.last_mut().unwrap()
+            }
+        };
+        for scene in r.scenes {
+            match renderer.scenes.iter_mut().find(|s| s.name == scene.name) {
+                Some(s) => {
+                    for spp in scene.spps {
+                        if !s.spps.contains(&spp) {
+                            s.spps.push(spp);
+                        }
+                    }
+                    s.spps.sort();
+                }
+                None => renderer.scenes.push(scene),
+            }
+        }
+    }
+    serde_json::to_value(&config).expect("Error serializing config.")
+}
+
+/// Creates a workspace with the given main config (see `technique_config()`).
+///
+/// Binaries are not copied.
+pub fn gen_config_from(path: &Path, config: &serde_json::Value) {
+    init_workspace(path);
+    let config: Config = serde_json::from_value(config.clone()).expect("Error deserializing config.");
+    config.save(&path.join("configs").join(MAIN_CONFIG));
+    unixfs::symlink(MAIN_CONFIG, path.join("configs/.current.json")).unwrap();
+}
 
-    let data = fs::read_to_string(paths::scenes_path().join(".fbksd-scenes-cache.json"))
-        .expect("Failed reading the scenes file");
-    let all_scenes: Vec<wp::Renderer> =
-        serde_json::from_str(&data).expect("Failed deserializing the info json");
+/// Generates a config for the given technique and scenes.
+///
+/// Each scene is given with the spps it should run with (see `workspace::scenes_spps()`).
+/// Retired scenes are ignored.
+/// This does not uses the `fbksd` script.
+/// Binaries, and results are not copied.
+pub fn gen_config<'a, I, J, K, L>(path: &Path, denoisers: I, samplers: I, scenes: J)
+where
+    I: IntoIterator<Item = &'a K>,
+    J: IntoIterator<Item = (&'a L, &'a Vec<i32>)>,
+    K: AsRef<str> + 'a,
+    L: AsRef<str> + 'a,
+{
+    init_workspace(path);
+
+    let all_scenes = wp::read_scenes_cache();
     let mut scene_render_map: HashMap<String, String> = HashMap::new();
     for (r, s) in all_scenes.iter().flat_map(|r| {
         r.scenes
             .iter()
+            .filter(|s| !s.retired)
             .map(move |s| (r.renderer.clone(), s.name.clone()))
     }) {
         scene_render_map.insert(s.clone(), r.clone());
@@ -149,12 +483,12 @@ where
         config.add_technique("samplers", tech.as_ref());
     }
     let mut renderers: HashMap<String, Vec<Scene>> = HashMap::new();
-    for s in scenes {
+    for (s, spps) in scenes {
         let renderer_name = match scene_render_map.get(s.as_ref()) {
             Some(name) => name,
             None => continue,
         };
-        let config_scene = Scene::new(s.as_ref());
+        let config_scene = Scene::new(s.as_ref(), spps);
         match renderers.get_mut(renderer_name) {
             Some(scenes) => scenes.push(config_scene),
             None => {
@@ -166,17 +500,18 @@ where
         let renderer = Renderer::new(&r.0, r.1);
         config.renderers.push(renderer);
     }
-    config.set_spps(&SystemConfig::load().spps);
 
-    let config_file = path.join("configs/all.json");
+    let config_file = path.join("configs").join(MAIN_CONFIG);
     config.save(&config_file);
-    unixfs::symlink("all.json", path.join("configs/.current.json")).unwrap();
+    unixfs::symlink(MAIN_CONFIG, path.join("configs/.current.json")).unwrap();
 }
 
 /// Runs `fbksd config new` on the current directory.
 ///
+/// Retired scenes are removed from the generated config, and the scenes spps are set according to their metadata.
+/// If a suite is given, only its scenes are kept.
 /// Expects `fbksd` in the current PATH.
-pub fn fbksd_config() -> Result<(), ()> {
+pub fn fbksd_config(suite: Option<&Suite>) -> Result<(), ()> {
     let config = SystemConfig::load();
     let spps: Vec<String> = config.spps.iter().map(|i| i.to_string()).collect();
     let spps = spps.iter().map(|i| i.as_str());
@@ -200,5 +535,210 @@ pub fn fbksd_config() -> Result<(), ()> {
     if status.is_err() || !status.unwrap().success() {
         return Err(());
     }
+
+    let suite_scenes = suite.map(|s| s.scene_set());
+    let removed: HashSet<String> = wp::read_scenes_cache()
+        .into_iter()
+        .flat_map(|r| r.scenes.into_iter())
+        .filter(|s| s.retired || suite_scenes.as_ref().map_or(false, |scenes| !scenes.contains(&s.name)))
+        .map(|s| s.name)
+        .collect();
+    let config_file = Path::new("configs").join(MAIN_CONFIG);
+    let mut config = Config::load(&config_file);
+    config.remove_scenes(&removed);
+    config.set_spps(&wp::scenes_spps());
+    config.save(&config_file);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a workspace with a current config of two techniques and three scenes, returning the config data.
+    fn create_shards_workspace(workspace: &Path) -> String {
+        fs::create_dir_all(workspace.join("configs")).unwrap();
+        let mut config = Config::new();
+        config.add_technique("denoisers", "A");
+        config.add_technique("samplers", "B");
+        config.renderers.push(Renderer::new(
+            "pbrt-v3",
+            vec![Scene::new("s1", &[4]), Scene::new("s2", &[4]), Scene::new("s3", &[4])],
+        ));
+        config.save(&workspace.join("configs/all.json"));
+        unixfs::symlink("all.json", workspace.join("configs/.current.json")).unwrap();
+        fs::read_to_string(workspace.join("configs/all.json")).unwrap()
+    }
+
+    #[test]
+    fn test_write_config_shards() {
+        let root = std::env::temp_dir().join("fbksd-test-write-config-shards");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let workspace = root.join("workspace");
+        let shards = [root.join("shard-0"), root.join("shard-1")];
+        let main_config = create_shards_workspace(&workspace);
+        assert_eq!(shard_units(&workspace, ShardBy::Scene), 3);
+        assert_eq!(shard_units(&workspace, ShardBy::Technique), 2);
+
+        // shards hard link the workspace files (see `workspace::create_shards()`)
+        for shard in &shards {
+            fs::create_dir_all(shard.join("configs")).unwrap();
+            fs::hard_link(workspace.join("configs/all.json"), shard.join("configs/all.json")).unwrap();
+            unixfs::symlink("all.json", shard.join("configs/.current.json")).unwrap();
+        }
+        write_config_shards(&workspace, &shards, ShardBy::Scene);
+        let c0 = Config::load(&shards[0].join("configs/.current.json"));
+        let c1 = Config::load(&shards[1].join("configs/.current.json"));
+        assert_eq!(c0.renderers[0].scenes.len(), 2);
+        assert_eq!(c1.renderers[0].scenes.len(), 1);
+        assert_eq!(c1.filters.len(), 1);
+        assert_eq!(fs::read_to_string(workspace.join("configs/all.json")).unwrap(), main_config);
+
+        write_config_shards(&workspace, &shards, ShardBy::Technique);
+        let c0 = Config::load(&shards[0].join("configs/.current.json"));
+        let c1 = Config::load(&shards[1].join("configs/.current.json"));
+        assert_eq!((c0.filters.len(), c0.samplers.len()), (1, 0));
+        assert_eq!((c1.filters.len(), c1.samplers.len()), (0, 1));
+        assert_eq!(c1.renderers[0].scenes.len(), 3);
+        assert_eq!(fs::read_to_string(workspace.join("configs/all.json")).unwrap(), main_config);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_create_shards() {
+        if std::process::Command::new("rsync").arg("--version").output().is_err() {
+            eprintln!("rsync not found, test skipped");
+            return;
+        }
+        let root = std::env::temp_dir().join("fbksd-test-config-shards");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let workspace = root.join("workspace");
+        let shards = [root.join("shard-0"), root.join("shard-1")];
+        let main_config = create_shards_workspace(&workspace);
+
+        assert!(crate::workspace::create_shards(&workspace, &shards, ShardBy::Scene));
+        let c0 = Config::load(&shards[0].join("configs/.current.json"));
+        let c1 = Config::load(&shards[1].join("configs/.current.json"));
+        assert_eq!(c0.renderers[0].scenes.len(), 2);
+        assert_eq!(c1.renderers[0].scenes.len(), 1);
+        assert_eq!(fs::read_to_string(workspace.join("configs/all.json")).unwrap(), main_config);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_merge_configs() {
+        let mut base = Config::new();
+        base.add_technique("denoisers", "A");
+        base.renderers.push(Renderer::new(
+            "pbrt-v3",
+            vec![Scene::new("s1", &[4, 8]), Scene::new("s2", &[4])],
+        ));
+        base.renderers.push(Renderer::new("mitsuba", vec![Scene::new("s3", &[4])]));
+        let mut update = Config::new();
+        update.add_technique("denoisers", "A");
+        update.renderers.push(Renderer::new("pbrt-v3", vec![Scene::new("s1", &[16, 4])]));
+
+        let merged = merge_configs(
+            &serde_json::to_value(&base).unwrap(),
+            &serde_json::to_value(&update).unwrap(),
+        );
+        let merged: Config = serde_json::from_value(merged).unwrap();
+        assert_eq!(merged.filters.len(), 1);
+        assert_eq!(merged.renderers.len(), 2);
+        assert_eq!(merged.renderers[0].scenes[0].spps, vec![4, 8, 16]);
+        assert_eq!(merged.renderers[0].scenes[1].name, "s2");
+        assert_eq!(merged.renderers[1].scenes[0].name, "s3");
+    }
+
+    #[test]
+    fn test_write_unit_configs() {
+        let workspace = std::env::temp_dir().join("fbksd-test-unit-configs");
+        if workspace.exists() {
+            fs::remove_dir_all(&workspace).unwrap();
+        }
+        fs::create_dir_all(workspace.join("configs")).unwrap();
+        let mut config = Config::new();
+        config.add_technique("denoisers", "A");
+        config.renderers.push(Renderer::new(
+            "pbrt-v3",
+            vec![Scene::new("s1", &[4, 8]), Scene::new("s2", &[4])],
+        ));
+        config.save(&workspace.join("configs").join(MAIN_CONFIG));
+        unixfs::symlink(MAIN_CONFIG, workspace.join("configs/.current.json")).unwrap();
+
+        let units = write_unit_configs(&workspace);
+        assert_eq!(units.len(), 3);
+        assert_eq!((units[1].scene.as_str(), units[1].spp), ("s1", 8));
+        assert_eq!(units[2].results, vec![PathBuf::from("results/.current/denoisers/A/default/s2")]);
+        let c = Config::load(&workspace.join("configs").join(&units[1].config));
+        assert_eq!(c.renderers[0].scenes.len(), 1);
+        assert_eq!(c.renderers[0].scenes[0].spps, vec![8]);
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn test_technique_config() {
+        let workspace = std::env::temp_dir().join("fbksd-test-technique-config");
+        if workspace.exists() {
+            fs::remove_dir_all(&workspace).unwrap();
+        }
+        fs::create_dir_all(workspace.join("configs")).unwrap();
+        let mut config = Config::new();
+        config.add_technique("denoisers", "A");
+        config.add_technique("samplers", "A");
+        config.renderers.push(Renderer::new(
+            "pbrt-v3",
+            vec![Scene::new("s1", &[4]), Scene::new("s2", &[4])],
+        ));
+        config.save(&workspace.join("configs").join(MAIN_CONFIG));
+
+        let value = technique_config(&workspace, "samplers", "A");
+        let c: Config = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!((c.filters.len(), c.samplers.len()), (0, 1));
+        assert_eq!(config_scenes(&value), vec!["s1", "s2"]);
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+
+    #[test]
+    fn test_write_resume_configs() {
+        let workspace = std::env::temp_dir().join("fbksd-test-resume-configs");
+        if workspace.exists() {
+            fs::remove_dir_all(&workspace).unwrap();
+        }
+        fs::create_dir_all(workspace.join("configs")).unwrap();
+        let mut config = Config::new();
+        config.add_technique("denoisers", "A");
+        config.add_technique("denoisers", "B");
+        config.add_technique("samplers", "C");
+        config.renderers.push(Renderer::new(
+            "pbrt-v3",
+            vec![Scene::new("s1", &[4, 8]), Scene::new("s2", &[4])],
+        ));
+        config.save(&workspace.join("configs").join(MAIN_CONFIG));
+        let logs = vec![
+            "denoisers/A/default/s1/4_0_log.json",
+            "denoisers/A/default/s1/8_0_log.json",
+            "denoisers/A/default/s2/4_0_log.json",
+            "denoisers/B/default/s1/4_0_log.json",
+            "samplers/C/default/s1/4_0_log.json",
+        ];
+        for log in logs {
+            let log = workspace.join("results/.current").join(log);
+            fs::create_dir_all(log.parent().unwrap()).unwrap();
+            fs::write(&log, "{}").unwrap();
+        }
+
+        let names = write_resume_configs(&workspace);
+        assert_eq!(names, vec!["resume-0.json"]);
+        let c = Config::load(&workspace.join("configs/resume-0.json"));
+        let techs: Vec<&str> = c.filters.iter().chain(&c.samplers).map(|t| t.name.as_str()).collect();
+        assert_eq!(techs, vec!["B", "C"]);
+        assert_eq!(c.renderers[0].scenes.len(), 2);
+        assert_eq!(c.renderers[0].scenes[0].spps, vec![8]);
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+}
@@ -1,45 +1,137 @@
+//! Container runtimes (docker and podman).
+
 use crate::paths;
+use crate::runtime::{self, Outcome, Runtime};
+use crate::system_config::Limits;
 use std::io;
-use std::process::Command;
-use std::process::ExitStatus;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use uuid::Uuid;
+
+/// Image used when a technique has no recorded image.
+pub static DEFAULT_IMAGE: &str = "fbksd";
+
+/// Returns the digest of a local docker image (the repository digest, or the image id if it was never pushed).
+pub fn image_digest(image: &str) -> Option<String> {
+    let output = Command::new("docker")
+        .args(&[
+            "image",
+            "inspect",
+            "--format",
+            "{{if .RepoDigests}}{{index .RepoDigests 0}}{{else}}{{.Id}}{{end}}",
+            image,
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
 /// Volume mapping from host to container:
-/// - <data>/scenes -> /mnt/fbksd-data/scenes
-/// - <data>/renderers -> /mnt/fbksd-data/renderers
-/// - <data>/tmp/workspace -> /mnt/fbksd-data/tmp/workspace
-
-pub fn run(command: &str, args: &[&str]) -> io::Result<ExitStatus> {
-    // docker run --runtime=nvidia --rm nvidia/cuda:9.1-devel gcc --version
-    // docker run -v <host_dir>:<cont_dir>:ro -w <working dir> -i -t <image> fbksd run
-    let lock_file = String::from(paths::LOCK_FILE) + ":" + paths::LOCK_FILE;
-    let workspace_mount = String::from(paths::tmp_workspace_path().to_str().unwrap())
-        + ":/mnt/fbksd-data/tmp/workspace";
-    let renderers_mount = String::from(paths::renderers_path().to_str().unwrap())
-        + ":/mnt/fbksd-data/tmp/workspace/renderers:ro";
-    let scenes_mount = String::from(paths::scenes_path().to_str().unwrap())
-        + ":/mnt/fbksd-data/tmp/workspace/scenes:ro";
-    let mut docker_args = vec![
-        "run",
-        "--runtime=nvidia",
-        "-v",
-        &lock_file,
-        "-v",
-        &scenes_mount,
-        "-v",
-        &renderers_mount,
-        "-v",
-        &workspace_mount,
-        "-w",
-        "/mnt/fbksd-data/tmp/workspace",
-        "-i",
-        "-t",
-        "fbksd",
-        command,
+/// - <data>/scenes -> /mnt/fbksd-data/tmp/workspace/scenes (read-only)
+/// - <data>/renderers -> /mnt/fbksd-data/tmp/workspace/renderers (read-only)
+/// - <workspace> -> /mnt/fbksd-data/tmp/workspace
+fn mounts<'a>(workspace: &'a Path, scenes: &'a Path, renderers: &'a Path) -> Vec<(&'a Path, &'static str)> {
+    vec![
+        (Path::new(paths::LOCK_FILE), paths::LOCK_FILE),
+        (scenes, "/mnt/fbksd-data/tmp/workspace/scenes:ro"),
+        (renderers, "/mnt/fbksd-data/tmp/workspace/renderers:ro"),
+        (workspace, "/mnt/fbksd-data/tmp/workspace"),
+    ]
+}
+
+/// Arguments for `<docker|podman> run` executing a command in the workspace.
+fn run_args(
+    extra_args: &[&str],
+    name: &str,
+    limits: &Limits,
+    mounts: &[(&Path, &str)],
+    image: &str,
+    command: &str,
+    args: &[&str],
+) -> Vec<String> {
+    let mut run_args = vec![
+        String::from("run"),
+        String::from("--rm"),
+        String::from("--name"),
+        String::from(name),
     ];
-    for s in args {
-        docker_args.push(s);
+    run_args.extend(extra_args.iter().map(|a| String::from(*a)));
+    if let Some(cpus) = limits.cpus {
+        run_args.push(format!("--cpus={}", cpus));
+    }
+    if let Some(memory) = &limits.memory {
+        run_args.push(format!("--memory={}", memory));
+    }
+    if let Some(pids) = limits.pids {
+        run_args.push(format!("--pids-limit={}", pids));
+    }
+    if limits.no_network {
+        run_args.push(String::from("--network=none"));
+    }
+    for (host, container) in mounts {
+        run_args.push(String::from("-v"));
+        run_args.push(format!("{}:{}", host.to_str().unwrap(), container));
+    }
+    run_args.extend(
+        vec!["-w", "/mnt/fbksd-data/tmp/workspace", "-i", image, command]
+            .into_iter()
+            .chain(args.iter().cloned())
+            .map(String::from),
+    );
+    run_args
+}
+
+/// Runs a container with `program` (docker or podman), killing it if it reaches the timeout.
+fn run_container(
+    program: &str,
+    extra_args: &[&str],
+    limits: &Limits,
+    image: &str,
+    workspace: &Path,
+    command: &str,
+    args: &[&str],
+) -> io::Result<Outcome> {
+    let name = format!("fbksd-run-{}", Uuid::new_v4());
+    let mounts = mounts(workspace, paths::scenes_path(), paths::renderers_path());
+    let args = run_args(extra_args, &name, limits, &mounts, image, command, args);
+    let child = Command::new(program).args(args).spawn()?;
+    // killing the client doesn't stop the container
+    runtime::wait_timeout(child, limits.timeout, |_| {
+        Command::new(program)
+            .arg("kill")
+            .arg(&name)
+            .stdout(Stdio::null())
+            .status()
+            .map(|_| ())
+    })
+}
+
+/// Docker runtime, optionally using the nvidia runtime (for techniques that need GPUs).
+pub struct Docker {
+    pub nvidia: bool,
+    pub limits: Limits,
+}
+
+impl Runtime for Docker {
+    fn run(&self, image: &str, workspace: &Path, command: &str, args: &[&str]) -> io::Result<Outcome> {
+        let extra_args: &[&str] = if self.nvidia { &["--runtime=nvidia"] } else { &[] };
+        run_container("docker", extra_args, &self.limits, image, workspace, command, args)
+    }
+}
+
+/// Podman runtime (rootless containers).
+pub struct Podman {
+    pub limits: Limits,
+}
+
+impl Runtime for Podman {
+    fn run(&self, image: &str, workspace: &Path, command: &str, args: &[&str]) -> io::Result<Outcome> {
+        run_container("podman", &[], &self.limits, image, workspace, command, args)
     }
-    Command::new("docker").args(docker_args).status()
 }
 
 #[cfg(test)]
@@ -47,7 +139,39 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_run() {
-        run("fbksd", &["--version"]).unwrap();
+    fn test_run_args() {
+        let limits = Limits {
+            cpus: Some(2.5),
+            memory: Some(String::from("8g")),
+            pids: None,
+            timeout: Some(60),
+            no_network: true,
+        };
+        let args = run_args(
+            &["--runtime=nvidia"],
+            "fbksd-run",
+            &limits,
+            &mounts(
+                Path::new("/data/tmp/workspace"),
+                Path::new("/data/scenes"),
+                Path::new("/data/renderers"),
+            ),
+            DEFAULT_IMAGE,
+            "fbksd",
+            &["results", "compute"],
+        );
+        assert_eq!(&args[..5], &["run", "--rm", "--name", "fbksd-run", "--runtime=nvidia"]);
+        for arg in &["--cpus=2.5", "--memory=8g", "--network=none"] {
+            assert!(args.contains(&String::from(*arg)));
+        }
+        assert!(!args.iter().any(|a| a.starts_with("--pids-limit")));
+        assert!(args.contains(&String::from("/data/scenes:/mnt/fbksd-data/tmp/workspace/scenes:ro")));
+        assert!(args.contains(&String::from("/data/tmp/workspace:/mnt/fbksd-data/tmp/workspace")));
+        assert!(!args.contains(&String::from("-t")));
+        assert_eq!(&args[args.len() - 4..], &[DEFAULT_IMAGE, "fbksd", "results", "compute"]);
+
+        let limits = Limits::default();
+        let args = run_args(&[], "fbksd-run", &limits, &[], DEFAULT_IMAGE, "fbksd", &["run"]);
+        assert!(!args.contains(&String::from("--network=none")));
     }
 }
@@ -0,0 +1,329 @@
+//! Conversion of EXR images to web images (png and jpg thumbnails).
+//!
+//! The benchmark outputs and the reference images are all EXR files. The page can only show web images, so they are
+//! generated here using the `convert` command from ImageMagick, which is expected in the PATH.
+//! Each destination folder keeps a stamp file (see `Stamp`) with the settings its images were generated with, so they
+//! are regenerated when the settings change.
+
+use crate::paths;
+use crate::system_config::{ImagesConfig, ToneMap};
+use glob::glob;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Size (in pixels) of the largest side of the thumbnails.
+pub static THUMB_SIZE: u32 = 256;
+
+/// Returns the path of the png image generated from the given EXR image.
+pub fn png_path(exr: &Path) -> PathBuf {
+    exr.with_extension("png")
+}
+
+/// Returns the path of the jpg thumbnail generated from the given EXR image.
+pub fn thumb_path(exr: &Path) -> PathBuf {
+    let mut thumb = String::from(exr.file_stem().unwrap().to_str().unwrap());
+    thumb.push_str(&format!("_thumb{}.jpg", THUMB_SIZE));
+    exr.with_file_name(thumb)
+}
+
+/// False-color scale used for error maps (from low to high error).
+static ERROR_MAP_COLORS: &[&str] = &["#440154", "#3b528b", "#21918c", "#5ec962", "#fde725"];
+
+/// Returns the metric acronym if the given image is an error map (`<spp>_0_<metric>_map.exr`).
+pub fn error_map_metric(exr: &Path) -> Option<String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^\d+_0_(.+)_map\.exr$").unwrap();
+    }
+    let name = exr.file_name()?.to_str()?;
+    RE.captures(name)
+        .map(|caps| String::from(caps.get(1).unwrap().as_str()))
+}
+
+/// Records how the web images of a folder were generated.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Stamp {
+    /// Conversion settings (unknown if None).
+    #[serde(default)]
+    pub config: Option<ImagesConfig>,
+    /// Scales used to convert the error maps, by metric acronym.
+    #[serde(default)]
+    pub error_map_scales: BTreeMap<String, f32>,
+    /// Generated images, relative to the folder.
+    #[serde(default)]
+    pub images: BTreeSet<PathBuf>,
+}
+
+impl Stamp {
+    /// Reads the stamp of the given folder (an empty stamp if it's missing or invalid).
+    pub fn read(dir: &Path) -> Stamp {
+        fs::read_to_string(dir.join(paths::IMAGES_STAMP_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// Saves the stamp in the given folder.
+    pub fn save(&self, dir: &Path) -> bool {
+        let data = serde_json::to_string_pretty(self).expect("Failed to serialize images stamp");
+        fs::write(dir.join(paths::IMAGES_STAMP_FILE), data).is_ok()
+    }
+
+    /// Returns true if the images were generated with a different configuration.
+    pub fn config_changed(&self, config: &ImagesConfig) -> bool {
+        self.config.as_ref() != Some(config)
+    }
+}
+
+/// Returns true if `dest` doesn't exist or is older than `src`.
+fn needs_update(src: &Path, dest: &Path) -> bool {
+    let dest_time = match fs::metadata(dest).and_then(|m| m.modified()) {
+        Ok(time) => time,
+        Err(_) => return true,
+    };
+    match fs::metadata(src).and_then(|m| m.modified()) {
+        Ok(src_time) => src_time > dest_time,
+        Err(_) => true,
+    }
+}
+
+/// Arguments applying the exposure and tone mapping to a linear image.
+fn tone_map_args(config: &ImagesConfig) -> Vec<String> {
+    let mut args = vec![String::from("-set"), String::from("colorspace"), String::from("RGB")];
+    if config.exposure != 0.0 {
+        args.push(String::from("-evaluate"));
+        args.push(String::from("Multiply"));
+        args.push(2f32.powf(config.exposure).to_string());
+    }
+    match config.tone_map {
+        ToneMap::Clamp => {}
+        ToneMap::Reinhard => {
+            args.push(String::from("-fx"));
+            args.push(String::from("u/(1+u)"));
+        }
+    }
+    args.push(String::from("-colorspace"));
+    args.push(String::from("sRGB"));
+    args
+}
+
+/// Converts an EXR image into a png image and a jpg thumbnail.
+///
+/// Returns false if the conversion failed.
+pub fn convert(exr: &Path, png: &Path, thumb: &Path, config: &ImagesConfig) -> bool {
+    for dir in &[png.parent(), thumb.parent()] {
+        if let Some(dir) = dir {
+            if !dir.is_dir() && fs::create_dir_all(dir).is_err() {
+                return false;
+            }
+        }
+    }
+    let mut args = vec![String::from(exr.to_str().unwrap())];
+    args.extend(tone_map_args(config));
+    args.extend(vec![
+        String::from("-write"),
+        String::from(png.to_str().unwrap()),
+        String::from("-thumbnail"),
+        format!("{}x{}", THUMB_SIZE, THUMB_SIZE),
+        String::from(thumb.to_str().unwrap()),
+    ]);
+    let status = Command::new("convert")
+        .args(&args)
+        .stdout(Stdio::null())
+        .status();
+    if status.is_err() || !status.unwrap().success() {
+        return false;
+    }
+    true
+}
+
+/// Converts an EXR error map into a false-color png image.
+///
+/// The error values are mapped from `[0, scale]` to the false-color scale, so maps from the same metric are comparable.
+pub fn convert_error_map(exr: &Path, png: &Path, scale: f32) -> bool {
+    if let Some(dir) = png.parent() {
+        if !dir.is_dir() && fs::create_dir_all(dir).is_err() {
+            return false;
+        }
+    }
+    let mut args = vec![
+        String::from(exr.to_str().unwrap()),
+        String::from("-set"),
+        String::from("colorspace"),
+        String::from("RGB"),
+        String::from("-colorspace"),
+        String::from("Gray"),
+        String::from("-evaluate"),
+        String::from("Multiply"),
+        (1.0 / scale).to_string(),
+        String::from("-clamp"),
+        String::from("("),
+    ];
+    args.extend(ERROR_MAP_COLORS.iter().map(|c| format!("xc:{}", c)));
+    args.extend(vec![
+        String::from("+append"),
+        String::from("-resize"),
+        String::from("256x1!"),
+        String::from(")"),
+        String::from("-clut"),
+        String::from(png.to_str().unwrap()),
+    ]);
+    let status = Command::new("convert")
+        .args(&args)
+        .stdout(Stdio::null())
+        .status();
+    if status.is_err() || !status.unwrap().success() {
+        return false;
+    }
+    true
+}
+
+/// Converts the EXR image only if its web images are missing or older than it.
+pub fn convert_if_changed(exr: &Path, png: &Path, thumb: &Path, config: &ImagesConfig) -> bool {
+    if !needs_update(exr, png) && !needs_update(exr, thumb) {
+        return true;
+    }
+    convert(exr, png, thumb, config)
+}
+
+/// Converts all EXR images from the `src` tree into web images in the same relative location in `dest`.
+///
+/// Only images whose sources changed are regenerated, or none of the existing ones if `ignore_existing` is true. All
+/// of them are regenerated if the conversion settings changed since the last export (see `Stamp`).
+/// Error maps are converted to false-color images (without thumbnails) using the scale of their metric from
+/// `error_map_scales` (1.0 if missing).
+/// Web images generated by a previous export that have no corresponding EXR in `src` anymore are removed.
+pub fn export_dir(
+    src: &Path,
+    dest: &Path,
+    ignore_existing: bool,
+    config: &ImagesConfig,
+    error_map_scales: &HashMap<String, f32>,
+) -> bool {
+    let mut ok = true;
+    let previous = Stamp::read(dest);
+    let config_changed = previous.config_changed(config);
+    let mut stamp = Stamp::default();
+    let pattern = src.join("**/*.exr");
+    for entry in glob(pattern.to_str().unwrap()).expect("Failed to read glob pattern") {
+        if let Ok(exr) = entry {
+            let rel = exr.strip_prefix(src).unwrap();
+            let png = dest.join(png_path(rel));
+            stamp.images.insert(png_path(rel));
+            if let Some(metric) = error_map_metric(&exr) {
+                let scale = *error_map_scales.get(&metric).unwrap_or(&1.0);
+                stamp.error_map_scales.insert(metric.clone(), scale);
+                let scale_changed = previous.error_map_scales.get(&metric) != Some(&scale);
+                if !scale_changed && ((ignore_existing && png.exists()) || !needs_update(&exr, &png)) {
+                    continue;
+                }
+                if !convert_error_map(&exr, &png, scale) {
+                    log::error!("failed to convert error map {:?}", exr);
+                    ok = false;
+                }
+                continue;
+            }
+            let thumb = dest.join(thumb_path(rel));
+            stamp.images.insert(thumb_path(rel));
+            if !config_changed && ignore_existing && png.exists() && thumb.exists() {
+                continue;
+            }
+            let converted = if config_changed {
+                convert(&exr, &png, &thumb, config)
+            } else {
+                convert_if_changed(&exr, &png, &thumb, config)
+            };
+            if !converted {
+                log::error!("failed to convert image {:?}", exr);
+                ok = false;
+            }
+        }
+    }
+
+    // remove stale web images
+    for rel in previous.images.difference(&stamp.images) {
+        let img = dest.join(rel);
+        if img.exists() && fs::remove_file(&img).is_err() {
+            ok = false;
+        }
+    }
+
+    // failed images are retried with the new settings in the next export
+    if ok {
+        stamp.config = Some(config.clone());
+    } else {
+        stamp.config = previous.config;
+        stamp.error_map_scales = previous.error_map_scales;
+    }
+    if !dest.is_dir() && stamp.images.is_empty() {
+        return ok;
+    }
+    stamp.save(dest) && ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp() {
+        let dir = std::env::temp_dir().join("fbksd-test-images-stamp");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let config = ImagesConfig::default();
+        assert!(Stamp::read(&dir).config_changed(&config));
+
+        let mut stamp = Stamp {
+            config: Some(config.clone()),
+            ..Default::default()
+        };
+        stamp.images.insert(PathBuf::from("scene/16_0.png"));
+        assert!(stamp.save(&dir));
+        let stamp = Stamp::read(&dir);
+        assert!(!stamp.config_changed(&config));
+        assert!(stamp.config_changed(&ImagesConfig {
+            tone_map: ToneMap::Reinhard,
+            exposure: 0.0,
+        }));
+        assert!(stamp.config_changed(&ImagesConfig {
+            tone_map: ToneMap::Clamp,
+            exposure: 1.0,
+        }));
+        assert!(stamp.images.contains(Path::new("scene/16_0.png")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_dir_removes_only_generated() {
+        let root = std::env::temp_dir().join("fbksd-test-images-export");
+        let _ = fs::remove_dir_all(&root);
+        let src = root.join("src");
+        let dest = root.join("dest");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(dest.join("scene")).unwrap();
+        for name in &["16_0.png", "16_0_thumb256.jpg", "16_0_mse_map.png", "extra.png"] {
+            fs::write(dest.join("scene").join(name), "").unwrap();
+        }
+        let mut stamp = Stamp {
+            config: Some(ImagesConfig::default()),
+            ..Default::default()
+        };
+        for name in &["16_0.png", "16_0_thumb256.jpg", "16_0_mse_map.png"] {
+            stamp.images.insert(Path::new("scene").join(name));
+        }
+        assert!(stamp.save(&dest));
+
+        // the EXR images were removed from the source
+        assert!(export_dir(&src, &dest, false, &ImagesConfig::default(), &HashMap::new()));
+        assert!(!dest.join("scene/16_0.png").exists());
+        assert!(!dest.join("scene/16_0_thumb256.jpg").exists());
+        assert!(!dest.join("scene/16_0_mse_map.png").exists());
+        assert!(dest.join("scene/extra.png").exists());
+        assert!(Stamp::read(&dest).images.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
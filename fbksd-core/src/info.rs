@@ -0,0 +1,346 @@
+//! Validation of the techniques `info.json` files (see `registry::Technique`).
+//!
+//! The file is checked as plain json, so all problems are found in one pass instead of failing on the first one.
+
+use crate::system_config::{BuildPolicy, SystemConfig};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Maximum length of a technique short name (it's used in paths and in the results page).
+pub static MAX_SHORT_NAME_LEN: usize = 32;
+/// cmake build types allowed in the `build` section.
+pub static BUILD_TYPES: &[&str] = &["Debug", "Release", "RelWithDebInfo", "MinSizeRel"];
+/// Generators allowed in the `build` section.
+pub static GENERATORS: &[&str] = &["Make", "Ninja"];
+/// cmake definitions set by fbksd-ci itself.
+static RESERVED_DEFINITIONS: &[&str] = &["CMAKE_BUILD_TYPE", "CMAKE_INSTALL_PREFIX"];
+
+/// Problem found in an `info.json` file.
+#[derive(Debug, PartialEq)]
+pub struct InfoProblem {
+    /// Path of the field (e.g. `versions[0].executable`), empty for the whole file.
+    pub field: String,
+    pub message: String,
+}
+
+impl InfoProblem {
+    fn new(field: &str, message: &str) -> InfoProblem {
+        InfoProblem {
+            field: String::from(field),
+            message: String::from(message),
+        }
+    }
+}
+
+impl fmt::Display for InfoProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.field, self.message)
+        }
+    }
+}
+
+/// Returns true if the citation is a single BibTeX entry (`@type{key, ...}`).
+fn is_bibtex(citation: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(?s)^@[A-Za-z]+\s*\{\s*[^,\s{}]+\s*,.*\}$").unwrap();
+    }
+    let citation = citation.trim();
+    if !RE.is_match(citation) {
+        return false;
+    }
+    // braces must be balanced, and the entry closed only at the end
+    let mut depth = 0;
+    for (i, c) in citation.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 || (depth == 0 && i != citation.len() - 1) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Checks the optional `build` section (see `registry::Build`).
+fn validate_build(build: &Value, policy: &BuildPolicy, problems: &mut Vec<InfoProblem>) {
+    lazy_static! {
+        static ref VALUE_RE: Regex = Regex::new(r"^[A-Za-z0-9_.,;:+=/ -]*$").unwrap();
+    }
+    let build = match build.as_object() {
+        Some(build) => build,
+        None => {
+            problems.push(InfoProblem::new("build", "expected an object"));
+            return;
+        }
+    };
+    let one_of = |field: &str, allowed: &[&str], problems: &mut Vec<InfoProblem>| match build.get(field) {
+        None => {}
+        Some(Value::String(s)) if allowed.contains(&s.as_str()) => {}
+        Some(_) => problems.push(InfoProblem::new(
+            &format!("build.{}", field),
+            &format!("must be one of: {}", allowed.join(", ")),
+        )),
+    };
+    one_of("build_type", BUILD_TYPES, problems);
+    one_of("generator", GENERATORS, problems);
+    let in_range = |field: &str, max: u64, problems: &mut Vec<InfoProblem>| match build.get(field) {
+        None => {}
+        Some(v) if v.as_u64().map_or(false, |v| v >= 1 && v <= max) => {}
+        Some(_) => problems.push(InfoProblem::new(
+            &format!("build.{}", field),
+            &format!("must be an integer from 1 to {}", max),
+        )),
+    };
+    in_range("jobs", policy.max_jobs as u64, problems);
+    in_range("timeout", policy.max_timeout, problems);
+    match build.get("definitions") {
+        None => {}
+        Some(Value::Object(definitions)) => {
+            for (name, value) in definitions {
+                let field = format!("build.definitions.{}", name);
+                if RESERVED_DEFINITIONS.contains(&name.as_str()) || !policy.allows_definition(name) {
+                    problems.push(InfoProblem::new(&field, "definition not allowed"));
+                }
+                match value.as_str() {
+                    Some(value) if VALUE_RE.is_match(value) => {}
+                    Some(_) => problems.push(InfoProblem::new(&field, "value has invalid characters")),
+                    None => problems.push(InfoProblem::new(&field, "expected a string")),
+                }
+            }
+        }
+        Some(_) => problems.push(InfoProblem::new("build.definitions", "expected an object")),
+    }
+    for field in build.keys() {
+        if !["build_type", "generator", "jobs", "definitions", "timeout"].contains(&field.as_str()) {
+            problems.push(InfoProblem::new(&format!("build.{}", field), "unknown field"));
+        }
+    }
+}
+
+/// Checks the contents of an `info.json` file.
+///
+/// The build settings are checked against the given policy. If the technique install folder is given, the versions
+/// executables are checked too.
+pub fn validate(data: &str, install: Option<&Path>, policy: &BuildPolicy) -> Vec<InfoProblem> {
+    lazy_static! {
+        static ref SHORT_NAME_RE: Regex = Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_-]*$").unwrap();
+    }
+    let info: Value = match serde_json::from_str(data) {
+        Ok(info) => info,
+        Err(err) => return vec![InfoProblem::new("", &format!("invalid json: {}", err))],
+    };
+    if !info.is_object() {
+        return vec![InfoProblem::new("", "expected an object")];
+    }
+    let mut problems = Vec::new();
+    let mut string = |field: &str, value: Option<&Value>| -> Option<String> {
+        match value {
+            None => problems.push(InfoProblem::new(field, "missing")),
+            Some(Value::String(s)) => return Some(s.clone()),
+            Some(_) => problems.push(InfoProblem::new(field, "expected a string")),
+        }
+        None
+    };
+    let technique_type = string("technique_type", info.get("technique_type"));
+    let short_name = string("short_name", info.get("short_name"));
+    let full_name = string("full_name", info.get("full_name"));
+    string("comment", info.get("comment"));
+    let citation = string("citation", info.get("citation"));
+
+    if let Some(t) = technique_type {
+        if t != "DENOISER" && t != "SAMPLER" {
+            problems.push(InfoProblem::new("technique_type", "must be \"DENOISER\" or \"SAMPLER\""));
+        }
+    }
+    if let Some(name) = short_name {
+        if name.is_empty() || name.len() > MAX_SHORT_NAME_LEN {
+            problems.push(InfoProblem::new(
+                "short_name",
+                &format!("must have 1 to {} characters", MAX_SHORT_NAME_LEN),
+            ));
+        }
+        if !name.is_empty() && !SHORT_NAME_RE.is_match(&name) {
+            problems.push(InfoProblem::new(
+                "short_name",
+                "only letters, digits, \"_\" and \"-\" are allowed (starting with a letter or digit)",
+            ));
+        }
+    }
+    if let Some(name) = full_name {
+        if name.trim().is_empty() {
+            problems.push(InfoProblem::new("full_name", "must not be empty"));
+        }
+    }
+    if let Some(citation) = citation {
+        if !citation.trim().is_empty() && !is_bibtex(&citation) {
+            problems.push(InfoProblem::new("citation", "must be empty or a single BibTeX entry"));
+        }
+    }
+
+    if let Some(build) = info.get("build") {
+        validate_build(build, policy, &mut problems);
+    }
+
+    // versions (optional, only the default one is allowed)
+    let versions = match info.get("versions") {
+        None => return problems,
+        Some(Value::Array(versions)) => versions,
+        Some(_) => {
+            problems.push(InfoProblem::new("versions", "expected an array"));
+            return problems;
+        }
+    };
+    if versions.len() > 1 {
+        problems.push(InfoProblem::new("versions", "only one version is allowed"));
+    }
+    for (i, version) in versions.iter().enumerate() {
+        let field = |name: &str| format!("versions[{}].{}", i, name);
+        if !version.is_object() {
+            problems.push(InfoProblem::new(&format!("versions[{}]", i), "expected an object"));
+            continue;
+        }
+        let mut string = |name: &str| -> Option<String> {
+            match version.get(name) {
+                None => problems.push(InfoProblem::new(&field(name), "missing")),
+                Some(Value::String(s)) => return Some(s.clone()),
+                Some(_) => problems.push(InfoProblem::new(&field(name), "expected a string")),
+            }
+            None
+        };
+        let name = string("name");
+        string("comment");
+        let executable = string("executable");
+        if let Some(name) = name {
+            if name != "default" {
+                problems.push(InfoProblem::new(&field("name"), "must be \"default\""));
+            }
+        }
+        let executable = match executable {
+            Some(executable) => executable,
+            None => continue,
+        };
+        let path = Path::new(&executable);
+        if executable.is_empty() || path.is_absolute() || path.components().any(|c| c.as_os_str() == "..") {
+            problems.push(InfoProblem::new(
+                &field("executable"),
+                "must be a path relative to the install folder",
+            ));
+            continue;
+        }
+        if let Some(install) = install {
+            let is_executable = fs::metadata(install.join(path))
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false);
+            if !is_executable {
+                problems.push(InfoProblem::new(
+                    &field("executable"),
+                    &format!("\"{}\" was not installed (or is not executable)", executable),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks an `info.json` file with the system build policy (see `validate()`).
+pub fn validate_file(path: &Path, install: Option<&Path>) -> Vec<InfoProblem> {
+    match fs::read_to_string(path) {
+        Ok(data) => validate(&data, install, &SystemConfig::load().build),
+        Err(err) => vec![InfoProblem::new("", &format!("failed to read {}: {}", path.display(), err))],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let valid = r#"{
+            "technique_type": "DENOISER",
+            "short_name": "NFOR",
+            "full_name": "Nonlinearly Weighted First-order Regression",
+            "comment": "",
+            "citation": "@article{nfor2016,\n title = {{NFOR}},\n year = {2016}\n}",
+            "versions": [{"name": "default", "comment": "", "executable": "nfor"}]
+        }"#;
+        assert_eq!(validate(valid, None, &BuildPolicy::default()), vec![]);
+
+        let invalid = r#"{
+            "technique_type": "FILTER",
+            "short_name": "my filter",
+            "comment": 1,
+            "citation": "Some paper, 2016",
+            "versions": [{"name": "v2", "comment": "", "executable": "../nfor"}]
+        }"#;
+        let fields: Vec<String> = validate(invalid, None, &BuildPolicy::default()).into_iter().map(|p| p.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "full_name",
+                "comment",
+                "technique_type",
+                "short_name",
+                "citation",
+                "versions[0].name",
+                "versions[0].executable",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_build() {
+        let info = |build: &str| {
+            format!(
+                r#"{{"technique_type": "DENOISER", "short_name": "D", "full_name": "D", "comment": "",
+                "citation": "", "build": {}}}"#,
+                build
+            )
+        };
+        let policy = BuildPolicy {
+            allowed_definitions: vec![String::from("CMAKE_CUDA_ARCHITECTURES"), String::from("WITH_*")],
+            max_jobs: 4,
+            max_timeout: 600,
+        };
+        let valid = r#"{"build_type": "RelWithDebInfo", "generator": "Ninja", "jobs": 4, "timeout": 600,
+            "definitions": {"CMAKE_CUDA_ARCHITECTURES": "75;86", "WITH_OPENMP": "ON"}}"#;
+        assert_eq!(validate(&info(valid), None, &policy), vec![]);
+
+        let invalid = r#"{"generator": "Xcode", "jobs": 16, "definitions": {"CMAKE_INSTALL_PREFIX": "/",
+            "WITH_X": "$(rm -rf)"}}"#;
+        let fields: Vec<String> = validate(&info(invalid), None, &policy).into_iter().map(|p| p.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "build.generator",
+                "build.jobs",
+                "build.definitions.CMAKE_INSTALL_PREFIX",
+                "build.definitions.WITH_X",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_executable() {
+        let install = std::env::temp_dir().join("fbksd-test-info-install");
+        fs::create_dir_all(&install).unwrap();
+        let info = r#"{"technique_type": "SAMPLER", "short_name": "S", "full_name": "S", "comment": "",
+            "citation": "", "versions": [{"name": "default", "comment": "", "executable": "missing"}]}"#;
+        let problems = validate(info, Some(&install), &BuildPolicy::default());
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].field, "versions[0].executable");
+        fs::remove_dir_all(&install).unwrap();
+    }
+}
@@ -3,9 +3,15 @@ pub mod utils;
 pub mod ci;
 pub mod config;
 pub mod docker;
+pub mod images;
+pub mod info;
+pub mod manifest;
 pub mod msgs;
 pub mod page;
 pub mod paths;
 pub mod registry;
+pub mod report;
+pub mod runtime;
+pub mod suites;
 pub mod system_config;
 pub mod workspace;
@@ -0,0 +1,285 @@
+//! Reproducibility manifests.
+//!
+//! Every time results are saved in a technique workspace, a manifest describing how they were produced (image, config,
+//! scenes, build flags, ...) is added to the workspace's `manifest.json` file. A manifest is enough to re-create an
+//! equivalent temporary workspace (see `workspace::create_tmp_manifest_workspace()`).
+//!
+//! Saves that run only some scenes (or spps) keep the config of the previous manifest for the others, so the last
+//! manifest always covers all results of the workspace.
+
+use crate::config;
+use crate::docker;
+use crate::paths;
+use crate::registry::{self as reg, TechniqueType};
+use crate::system_config::SystemConfig;
+use crate::workspace::{self as wp, SceneHashes};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Version of the fbksd core library that produced the manifest.
+pub static FBKSD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How a set of results of a workspace was produced.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Manifest {
+    pub group: TechniqueType,
+    pub id: String,
+    pub uuid: String,
+    pub date: DateTime<Utc>,
+    pub commit_sha: String,
+    pub docker_image: String,
+    /// Digest of the docker image when the run started (None if it couldn't be inspected).
+    pub docker_image_digest: Option<String>,
+    pub fbksd_version: String,
+    /// Benchmark suite the workspace was run with (all scenes if None).
+    pub suite: Option<String>,
+    /// Config the workspace results were produced with (only with this technique).
+    pub config: serde_json::Value,
+    /// Hashes of the config scenes.
+    pub scene_hashes: BTreeMap<String, SceneHashes>,
+    /// System spps.
+    pub spps: Vec<i32>,
+    pub host: String,
+    /// Flags the technique was configured with (see `fbksd-ci install`).
+    pub build_flags: Vec<String>,
+}
+
+impl Manifest {
+    /// Creates the manifest of the results of a technique in a workspace, from its main config.
+    ///
+    /// The image digest is the one recorded when the workspace run started (see `record_run_start()`).
+    pub fn new(group: &TechniqueType, id: &str, uuid: &str, short_name: &str, workspace: &Path) -> Manifest {
+        let reg = reg::Registry::load();
+        let docker_image = reg
+            .workspace_docker_image(id, uuid)
+            .cloned()
+            .unwrap_or_else(|| String::from(docker::DEFAULT_IMAGE));
+        let config = config::technique_config(workspace, group.as_str(), short_name);
+        let scenes = config::config_scenes(&config);
+        let scene_hashes = wp::read_scenes_cache()
+            .into_iter()
+            .flat_map(|r| r.scenes.into_iter())
+            .filter(|s| scenes.contains(&s.name))
+            .map(|s| (s.name, s.hashes))
+            .collect();
+        Manifest {
+            group: *group,
+            id: String::from(id),
+            uuid: String::from(uuid),
+            date: Utc::now(),
+            commit_sha: reg.workspace_commit_sha(id, uuid).cloned().unwrap_or_default(),
+            docker_image_digest: read_run_info(workspace).docker_image_digest,
+            docker_image,
+            fbksd_version: String::from(FBKSD_VERSION),
+            suite: reg.workspace_suite(id, uuid).cloned(),
+            config,
+            scene_hashes,
+            spps: SystemConfig::load().spps,
+            host: hostname(),
+            build_flags: read_build_flags(&paths::tech_install_path(group, id, uuid)),
+        }
+    }
+
+    /// Returns what differs between the current system and the one the manifest was produced in.
+    pub fn differences(&self) -> Vec<String> {
+        let current: BTreeMap<String, SceneHashes> = wp::read_scenes_cache()
+            .into_iter()
+            .flat_map(|r| r.scenes.into_iter())
+            .map(|s| (s.name, s.hashes))
+            .collect();
+        let digest = self
+            .docker_image_digest
+            .as_ref()
+            .and_then(|_| docker::image_digest(&self.docker_image));
+        self.differences_from(&current, digest.as_deref())
+    }
+
+    /// Returns what differs from the given scene hashes and image digest (see `differences()`).
+    fn differences_from(&self, scene_hashes: &BTreeMap<String, SceneHashes>, digest: Option<&str>) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if self.fbksd_version != FBKSD_VERSION {
+            diffs.push(format!("fbksd version: {} (now {})", self.fbksd_version, FBKSD_VERSION));
+        }
+        for (scene, hashes) in &self.scene_hashes {
+            match scene_hashes.get(scene) {
+                Some(h) if h == hashes => {}
+                Some(_) => diffs.push(format!("scene {} changed", scene)),
+                None => diffs.push(format!("scene {} not found", scene)),
+            }
+        }
+        if let Some(own) = &self.docker_image_digest {
+            if digest != Some(own.as_str()) {
+                diffs.push(format!("docker image {} is not {}", self.docker_image, own));
+            }
+        }
+        diffs
+    }
+
+    /// Includes the config (and scene hashes) of the previous manifest of the workspace for the scenes and spps this
+    /// one didn't run.
+    fn include_previous(&mut self, previous: &Manifest) {
+        self.config = config::merge_configs(&previous.config, &self.config);
+        for (scene, hashes) in &previous.scene_hashes {
+            self.scene_hashes.entry(scene.clone()).or_insert_with(|| hashes.clone());
+        }
+    }
+}
+
+/// Information recorded in a temporary workspace when its run starts.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct RunInfo {
+    docker_image_digest: Option<String>,
+}
+
+fn read_run_info(workspace: &Path) -> RunInfo {
+    fs::read_to_string(workspace.join(paths::RUN_INFO_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Records the digest of the docker image a temporary workspace runs with, when its run starts.
+pub fn record_run_start(workspace: &Path, docker_image_digest: Option<String>) {
+    let info = RunInfo { docker_image_digest };
+    let data = serde_json::to_string_pretty(&info).expect("Failed to serialize run info");
+    fs::write(workspace.join(paths::RUN_INFO_FILE), &data).expect("Failed to save run info file");
+}
+
+fn hostname() -> String {
+    Command::new("hostname")
+        .output()
+        .ok()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the build flags saved in a technique install folder (empty if not saved).
+fn read_build_flags(install: &Path) -> Vec<String> {
+    fs::read_to_string(install.join(paths::TECH_BUILD_FLAGS_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Reads the manifests from a file (see `record()`).
+pub fn read(path: &Path) -> Vec<Manifest> {
+    match fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data).expect("Failed to deserialize manifest file"),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Adds a manifest to its workspace's manifest file (oldest first).
+pub fn record(manifest: Manifest) {
+    let path = paths::tech_manifest_path(&manifest.group, &manifest.id, &manifest.uuid);
+    append(&path, manifest);
+}
+
+/// Adds a manifest to a manifest file, including what it didn't run from the previous one.
+fn append(path: &Path, mut manifest: Manifest) {
+    let mut manifests = read(path);
+    if let Some(previous) = manifests.last() {
+        manifest.include_previous(previous);
+    }
+    manifests.push(manifest);
+    let data = serde_json::to_string_pretty(&manifests).expect("Failed to serialize manifests");
+    fs::write(path, &data).expect("Failed to save manifest file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(scene: &str) -> SceneHashes {
+        SceneHashes {
+            scene: format!("{}-scene", scene),
+            reference: format!("{}-ref", scene),
+        }
+    }
+
+    fn manifest(scenes: &[(&str, Vec<i32>)]) -> Manifest {
+        let config = serde_json::json!({
+            "renderers": [{
+                "name": "pbrt-v3",
+                "scenes": scenes
+                    .iter()
+                    .map(|(name, spps)| serde_json::json!({"name": name, "spps": spps}))
+                    .collect::<Vec<_>>(),
+            }],
+            "filters": [{"name": "A", "versions": ["default"]}],
+            "samplers": [],
+        });
+        Manifest {
+            group: TechniqueType::DENOISER,
+            id: String::from("12"),
+            uuid: String::from("u1"),
+            date: Utc::now(),
+            commit_sha: String::from("abcd1234"),
+            docker_image: String::from("fbksd-cuda"),
+            docker_image_digest: Some(String::from("sha256:1")),
+            fbksd_version: String::from(FBKSD_VERSION),
+            suite: None,
+            config,
+            scene_hashes: scenes.iter().map(|(name, _)| (name.to_string(), hashes(name))).collect(),
+            spps: vec![4, 8],
+            host: String::from("host"),
+            build_flags: vec![String::from("-DCMAKE_BUILD_TYPE=Release")],
+        }
+    }
+
+    #[test]
+    fn test_record_read() {
+        let path = std::env::temp_dir().join("fbksd-test-manifest.json");
+        if path.exists() {
+            fs::remove_file(&path).unwrap();
+        }
+        assert!(read(&path).is_empty());
+        append(&path, manifest(&[("s1", vec![4, 8]), ("s2", vec![4, 8])]));
+        // partial save: only s1 with 16 spp
+        let mut partial = manifest(&[("s1", vec![16])]);
+        partial.docker_image_digest = Some(String::from("sha256:2"));
+        append(&path, partial);
+
+        let manifests = read(&path);
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(config::config_scenes(&manifests[0].config), vec!["s1", "s2"]);
+        let last = &manifests[1];
+        assert_eq!(last.docker_image_digest.as_deref(), Some("sha256:2"));
+        assert_eq!(last.build_flags, vec!["-DCMAKE_BUILD_TYPE=Release"]);
+        assert_eq!(config::config_scenes(&last.config), vec!["s1", "s2"]);
+        assert_eq!(last.config["renderers"][0]["scenes"][0]["spps"], serde_json::json!([4, 8, 16]));
+        assert_eq!(last.scene_hashes.len(), 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_differences() {
+        let m = manifest(&[("s1", vec![4]), ("s2", vec![4])]);
+        let mut current: BTreeMap<String, SceneHashes> =
+            vec![("s1", hashes("s1")), ("s2", hashes("s2"))]
+                .into_iter()
+                .map(|(name, h)| (String::from(name), h))
+                .collect();
+        assert!(m.differences_from(&current, Some("sha256:1")).is_empty());
+
+        current.get_mut("s1").unwrap().scene = String::from("changed");
+        current.remove("s2");
+        assert_eq!(
+            m.differences_from(&current, Some("sha256:2")),
+            vec![
+                "scene s1 changed",
+                "scene s2 not found",
+                "docker image fbksd-cuda is not sha256:1",
+            ]
+        );
+
+        let mut old = manifest(&[]);
+        old.fbksd_version = String::from("0.0.0");
+        old.docker_image_digest = None;
+        assert_eq!(old.differences_from(&current, None).len(), 1);
+    }
+}
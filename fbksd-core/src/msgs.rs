@@ -16,10 +16,30 @@ pub enum Msg {
     PublishPublic(ProjectInfo, String),
     CanRun(ProjectInfo),
     DeleteWorkspace(ProjectInfo, String),
+    GenerateReport(ProjectInfo, String),
     End,
     Invalid,
 }
 
+impl Msg {
+    /// Returns the project the message is about (if any).
+    pub fn project(&self) -> Option<&ProjectInfo> {
+        use Msg::*;
+        match self {
+            Register(info, _)
+            | SaveResults(info, _)
+            | CanRun(info)
+            | PublishPrivate(info, _)
+            | InitMissingScenesWP(info, _)
+            | UpdateResults(info, _)
+            | PublishPublic(info, _)
+            | DeleteWorkspace(info, _)
+            | GenerateReport(info, _) => Some(info),
+            End | Invalid => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Error {
     InvalidMessage,
@@ -1,6 +1,6 @@
 use glob::glob;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::os::unix::fs as unixfs;
 use std::path::Path;
@@ -16,6 +16,9 @@ pub struct Scene {
     pub renderer: String,
     pub reference: String,
     pub thumbnail: String,
+    /// Retired scenes have results, but should not be included in aggregates.
+    pub retired: bool,
+    pub tags: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -44,6 +47,7 @@ pub struct Metric {
     pub reference: String,
     pub lower_is_better: bool,
     pub has_error_map: bool,
+    pub error_map_scale: f32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,6 +58,163 @@ pub struct Result {
     pub exec_time: i64,
     pub aborted: bool,
     pub metrics: HashMap<String, f32>,
+    /// Error map images (metric acronym -> path relative to the technique data folder).
+    pub error_maps: HashMap<String, String>,
+    /// Run logs (`stdout` or `stderr` -> path relative to the technique data folder). Only in private pages.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub logs: HashMap<String, String>,
+}
+
+/// Mean metric values of a technique (spp -> metric acronym -> value), over all active scenes, per scene tag and
+/// per suite.
+///
+/// Only the scenes all techniques of the group have results for are included, so the aggregates of different
+/// techniques are comparable.
+#[derive(Serialize, Deserialize)]
+pub struct Aggregate {
+    pub technique_id: i32,
+    pub all: BTreeMap<i32, BTreeMap<String, f32>>,
+    pub tags: BTreeMap<String, BTreeMap<i32, BTreeMap<String, f32>>>,
+    pub suites: BTreeMap<String, BTreeMap<i32, BTreeMap<String, f32>>>,
+    /// Number of scenes the aggregates include.
+    pub num_scenes: AggregateScenes,
+}
+
+/// Number of scenes (spp -> count) included in each aggregate (see `Aggregate`).
+///
+/// It's smaller than the number of scenes of the aggregate when not all techniques have results for all of them.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AggregateScenes {
+    pub all: BTreeMap<i32, usize>,
+    pub tags: BTreeMap<String, BTreeMap<i32, usize>>,
+    pub suites: BTreeMap<String, BTreeMap<i32, usize>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Suite {
+    pub id: String,
+    pub name: String,
+    pub version: u32,
+    pub description: String,
+    pub scenes_ids: Vec<i32>,
+    /// Techniques (ids) with results for all scenes of the suite.
+    pub complete_techniques: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Publication {
+    pub uuid: String,
+    pub commit: String,
+    pub date: String,
+    pub event: String,
+    pub num_scenes: usize,
+    /// Mean metric values (spp -> metric acronym -> value).
+    pub summary: BTreeMap<i32, BTreeMap<String, f32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct History {
+    pub technique_id: i32,
+    pub name: String,
+    pub publications: Vec<Publication>,
+}
+
+/// Returns an id derived from the given stable key (e.g. `"scene/<name>"`).
+///
+/// The same key always produces the same id, so ids don't change between page exports and can be referenced
+/// from outside the page. Ids are non-negative and fit in 31 bits (FNV-1a hash).
+pub fn stable_id(key: &str) -> i32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for b in key.bytes() {
+        hash ^= u32::from(b);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    (hash & 0x7fff_ffff) as i32
+}
+
+/// Returns the page id of a technique from its registry id.
+///
+/// Numeric (GitLab) ids are kept, namespaced ids (see `ci::Provider`) get a negative stable id so they never collide
+/// with them.
+pub fn technique_id(id: &str) -> i32 {
+    match id.parse() {
+        Ok(id) => id,
+        Err(_) => -stable_id(&format!("technique/{}", id)) - 1,
+    }
+}
+
+/// Returns the stable key of a scene.
+pub fn scene_key(scene_name: &str) -> String {
+    format!("scene/{}", scene_name)
+}
+
+/// Returns the stable key of a technique's result.
+pub fn result_key(group: &str, tech_id: i32, scene_name: &str, spp: i32) -> String {
+    format!("result/{}/{}/{}/{}", group, tech_id, scene_name, spp)
+}
+
+/// Page ids assigned to stable keys (see `scene_key()` and `result_key()`).
+///
+/// A new key gets its stable id (see `stable_id()`), or the stable id of the key salted with `#<n>` if that one is
+/// already taken by another key. The assigned ids are saved in the data folder (see `paths::page_ids_path()`), so
+/// they don't change between page exports, even when a new key collides with an existing one.
+#[derive(Debug, Default)]
+pub struct PageIds {
+    ids: BTreeMap<String, i32>,
+    used: HashSet<i32>,
+    changed: bool,
+}
+
+impl PageIds {
+    /// Loads the assigned ids.
+    pub fn load() -> PageIds {
+        PageIds::read(&paths::page_ids_path())
+    }
+
+    fn read(path: &Path) -> PageIds {
+        let ids: BTreeMap<String, i32> = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).expect("Failed to deserialize page ids file"),
+            Err(_) => BTreeMap::new(),
+        };
+        let used: HashSet<i32> = ids.values().cloned().collect();
+        assert_eq!(used.len(), ids.len(), "Page ids file has duplicated ids");
+        PageIds {
+            ids,
+            used,
+            changed: false,
+        }
+    }
+
+    /// Saves the assigned ids, if any new id was assigned since they were loaded.
+    pub fn save(&mut self) {
+        self.write(&paths::page_ids_path());
+    }
+
+    fn write(&mut self, path: &Path) {
+        if !self.changed {
+            return;
+        }
+        let data = serde_json::to_string_pretty(&self.ids).expect("Failed to serialize page ids");
+        fs::write(path, data).expect("Failed to save page ids file");
+        self.changed = false;
+    }
+
+    /// Returns the id of the given key, assigning a new one if needed.
+    pub fn get(&mut self, key: &str) -> i32 {
+        if let Some(id) = self.ids.get(key) {
+            return *id;
+        }
+        let mut id = stable_id(key);
+        let mut salt = 0;
+        while self.used.contains(&id) {
+            salt += 1;
+            id = stable_id(&format!("{}#{}", key, salt));
+        }
+        self.ids.insert(String::from(key), id);
+        self.used.insert(id);
+        self.changed = true;
+        id
+    }
 }
 
 /// Creates a new page directory with the given path.
@@ -133,4 +294,44 @@ mod tests {
 
     #[test]
     fn test_copy_public_page() {}
+
+    #[test]
+    fn test_stable_id() {
+        assert_eq!(stable_id(""), 0x011c_9dc5);
+        assert_eq!(stable_id("scene/bathroom"), stable_id("scene/bathroom"));
+        assert_ne!(stable_id(&scene_key("bathroom")), stable_id(&scene_key("kitchen")));
+        assert_ne!(
+            stable_id(&result_key("denoisers", 10, "bathroom", 4)),
+            stable_id(&result_key("denoisers", 10, "bathroom", 8))
+        );
+        assert!(stable_id(&result_key("samplers", 42, "kitchen", 1024)) >= 0);
+    }
+
+    #[test]
+    fn test_page_ids() {
+        let path = std::env::temp_dir().join("fbksd-test-page-ids.json");
+        let _ = fs::remove_file(&path);
+        // "s258" and "s596942" have the same 31-bit hash
+        let (a, b) = (scene_key("s596942"), scene_key("s258"));
+        assert_eq!(stable_id(&a), stable_id(&b));
+
+        let mut ids = PageIds::read(&path);
+        let id_a = ids.get(&a);
+        assert_eq!(id_a, stable_id(&a));
+        assert_eq!(ids.get(&a), id_a);
+        ids.write(&path);
+
+        // the colliding key added later gets a salted id, and the existing one is kept
+        let mut ids = PageIds::read(&path);
+        let id_b = ids.get(&b);
+        assert_ne!(id_b, id_a);
+        assert_eq!(id_b, stable_id(&format!("{}#1", b)));
+        ids.write(&path);
+
+        let mut ids = PageIds::read(&path);
+        assert_eq!(ids.get(&b), id_b);
+        assert_eq!(ids.get(&a), id_a);
+        assert!(!ids.changed);
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -6,8 +6,11 @@ use std::env;
 use std::path::{Path, PathBuf};
 
 static REGISTRY_FILE: &str = "registry.json";
+static RERUN_QUEUE_FILE: &str = "rerun-queue.json";
+static PAGE_IDS_FILE: &str = "page-ids.json";
 static CONFIG_FILE: &str = "config.json";
 static SCENES_DIR: &str = "scenes";
+static SUITES_DIR: &str = "suites";
 static IQA_DIR: &str = "iqa";
 static RENDERERS_DIR: &str = "renderers";
 static WORKSPACES_DIR: &str = "workspaces";
@@ -16,11 +19,21 @@ static SAMPLERS_WORKSPACES_DIR: &str = "samplers";
 static PAGE_DIR: &str = "page";
 static TMP_WORKSPACE_DIR: &str = "tmp/workspace";
 static PUBLIC_PAGE_DIR: &str = "public";
+static SCENES_CACHE_FILE: &str = ".fbksd-scenes-cache.json";
+static SCENES_PENDING_FILE: &str = ".fbksd-scenes-pending.json";
 static TECH_PUBLISHED_DIR: &str = "published";
 
 pub static LOCK_FILE: &str = "/var/lock/fbksd.lock";
 pub static TECH_INSTALL_DIR: &str = "install";
 pub static TECH_RESULTS_DIR: &str = "results";
+pub static TECH_SCENE_HASHES_FILE: &str = "scenes_hashes.json";
+pub static TECH_MANIFEST_FILE: &str = "manifest.json";
+/// File (in a temporary workspace) with information recorded when its run started (see `manifest::record_run_start()`).
+pub static RUN_INFO_FILE: &str = ".fbksd-run.json";
+/// File (in the install folder) with the cmake flags the technique was built with.
+pub static TECH_BUILD_FLAGS_FILE: &str = ".fbksd-build-flags.json";
+/// File (in a web images folder) recording how its images were generated (see `images::Stamp`).
+pub static IMAGES_STAMP_FILE: &str = ".fbksd-images.json";
 
 pub fn data_root() -> &'static Path {
     const VAR: &str = "FBKSD_DATA_ROOT";
@@ -39,6 +52,16 @@ pub fn registry_path() -> PathBuf {
     data_root().join(&REGISTRY_FILE)
 }
 
+/// File with the published results queued to run again.
+pub fn rerun_queue_path() -> PathBuf {
+    data_root().join(&RERUN_QUEUE_FILE)
+}
+
+/// File with the ids assigned to the page scenes and results (see `page::PageIds`).
+pub fn page_ids_path() -> PathBuf {
+    data_root().join(PAGE_IDS_FILE)
+}
+
 pub fn workspaces_path() -> &'static Path {
     lazy_static! {
         static ref PATH: PathBuf = data_root().join(&WORKSPACES_DIR);
@@ -53,6 +76,21 @@ pub fn scenes_path() -> &'static Path {
     &PATH
 }
 
+/// Cache file with all scenes found in the scenes folder.
+pub fn scenes_cache_path() -> PathBuf {
+    scenes_path().join(&SCENES_CACHE_FILE)
+}
+
+/// File with the scenes added to the cache that the published techniques didn't run yet.
+pub fn scenes_pending_path() -> PathBuf {
+    scenes_path().join(&SCENES_PENDING_FILE)
+}
+
+/// Folder with the benchmark suites definitions.
+pub fn suites_path() -> PathBuf {
+    data_root().join(&SUITES_DIR)
+}
+
 pub fn iqa_path() -> &'static Path {
     lazy_static! {
         static ref PATH: PathBuf = data_root().join(&IQA_DIR);
@@ -81,6 +119,16 @@ pub fn tmp_workspace_path() -> &'static Path {
     &PATH
 }
 
+/// Checkpoint file of the current run-all (see `workspace::Checkpoint`).
+pub fn run_checkpoint_path() -> PathBuf {
+    data_root().join("tmp/run-all-checkpoint.json")
+}
+
+/// Temporary workspace of a shard of a parallel run.
+pub fn tmp_shard_workspace_path(shard: usize) -> PathBuf {
+    data_root().join(format!("{}-shard-{}", TMP_WORKSPACE_DIR, shard))
+}
+
 pub fn public_page_path() -> &'static Path {
     lazy_static! {
         static ref PATH: PathBuf = data_root().join(&PUBLIC_PAGE_DIR);
@@ -121,6 +169,16 @@ pub fn tech_results_path(group: &TechniqueType, id: &str, uuid: &str) -> PathBuf
     tech_workspace_path(group, &id, &uuid).join(&TECH_RESULTS_DIR)
 }
 
+/// File with the hashes of the scenes a workspace's results were computed with.
+pub fn tech_scene_hashes_path(group: &TechniqueType, id: &str, uuid: &str) -> PathBuf {
+    tech_workspace_path(group, &id, &uuid).join(&TECH_SCENE_HASHES_FILE)
+}
+
+/// File with the reproducibility manifests of a workspace (see the `manifest` module).
+pub fn tech_manifest_path(group: &TechniqueType, id: &str, uuid: &str) -> PathBuf {
+    tech_workspace_path(group, &id, &uuid).join(&TECH_MANIFEST_FILE)
+}
+
 pub fn tech_published_wp_path(group: &TechniqueType, id: &str) -> PathBuf {
     tech_workspace_path(group, &id, &TECH_PUBLISHED_DIR)
 }
@@ -11,7 +11,7 @@ use chrono::{DateTime, Utc};
 use log;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use std::fs;
@@ -57,7 +57,7 @@ to_unspecified!(serde_json::error::Error);
 
 type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TechniqueType {
     DENOISER,
     SAMPLER,
@@ -88,6 +88,29 @@ pub struct Technique {
     pub citation: String,
     #[serde(default)]
     pub versions: Vec<Version>,
+    /// Build settings (the default cmake build if None).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<Build>,
+}
+
+/// Build settings of a technique (checked against the system `BuildPolicy` by `info::validate()`).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Build {
+    /// cmake build type (`Release` if None).
+    #[serde(default)]
+    pub build_type: Option<String>,
+    /// `Make` or `Ninja` (cmake's default if None).
+    #[serde(default)]
+    pub generator: Option<String>,
+    /// Number of parallel build jobs.
+    #[serde(default)]
+    pub jobs: Option<usize>,
+    /// Extra cmake definitions (`-D<name>=<value>`).
+    #[serde(default)]
+    pub definitions: BTreeMap<String, String>,
+    /// Build timeout, in seconds (the policy maximum if None).
+    #[serde(default)]
+    pub timeout: Option<u64>,
 }
 
 impl Technique {
@@ -122,6 +145,9 @@ struct Workspace {
     docker_image: String,
     status: WorkspaceStatus,
     creation_time: DateTime<Utc>,
+    /// Benchmark suite the workspace was run with (all scenes if None).
+    #[serde(default)]
+    suite: Option<String>,
 }
 
 impl Workspace {
@@ -132,14 +158,42 @@ impl Workspace {
             docker_image: info.docker_img.clone(),
             status: WorkspaceStatus::New,
             creation_time: Utc::now(),
+            suite: info.suite.clone(),
         }
     }
 }
 
+/// Mean metric values of a technique's results (spp -> metric acronym -> mean value).
+pub type Summary = BTreeMap<i32, BTreeMap<String, f32>>;
+
+/// Event that caused a history record.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum HistoryEvent {
+    /// A workspace was published.
+    Published,
+    /// The published workspace results were updated (e.g. after scenes were added).
+    ResultsUpdated,
+}
+
+/// Record of a technique's published results at some point in time.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryRecord {
+    pub uuid: String,
+    pub commit_sha: String,
+    pub date: DateTime<Utc>,
+    pub event: HistoryEvent,
+    /// Number of scenes with results.
+    pub num_scenes: usize,
+    pub summary: Summary,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Entry {
     name: String, // Last name obtained from a build.
     workspaces: Vec<Workspace>,
+    /// Records of all publications (kept even after the workspaces are removed).
+    #[serde(default)]
+    history: Vec<HistoryRecord>,
 }
 
 impl Entry {
@@ -232,6 +286,7 @@ impl Registry {
             Entry {
                 name: String::from(tech.short_name.as_str()),
                 workspaces: Vec::new(),
+                history: Vec::new(),
             },
         );
         log::trace!("Technique {} registered.", tech.short_name);
@@ -347,6 +402,80 @@ impl Registry {
         })
     }
 
+    /// Adds a record to the technique's publication history.
+    ///
+    /// The workspace `uuid` must belong to the technique.
+    /// A `ResultsUpdated` record is skipped if the results didn't change since the technique's last record.
+    pub fn add_history(
+        &mut self,
+        id: &str,
+        uuid: &str,
+        event: HistoryEvent,
+        num_scenes: usize,
+        summary: Summary,
+    ) -> Result<()> {
+        let commit_sha = match self.get_workspace_mut(id, uuid) {
+            Some(w) => w.commit_sha.clone(),
+            None => return Err(Error::Unspecified),
+        };
+        let (_, entry) = self.get_entry_mut(id).unwrap();
+        if event == HistoryEvent::ResultsUpdated {
+            if let Some(last) = entry.history.last() {
+                if last.uuid == uuid
+                    && last.commit_sha == commit_sha
+                    && last.num_scenes == num_scenes
+                    && last.summary == summary
+                {
+                    return Ok(());
+                }
+            }
+        }
+        entry.history.push(HistoryRecord {
+            uuid: String::from(uuid),
+            commit_sha,
+            date: Utc::now(),
+            event,
+            num_scenes,
+            summary,
+        });
+        Ok(())
+    }
+
+    /// Returns the publication history of all techniques of a group as (id, name, history) tuples.
+    pub fn get_history(
+        &self,
+        group: &TechniqueType,
+    ) -> impl Iterator<Item = (&String, &String, &Vec<HistoryRecord>)> {
+        let map = match group {
+            TechniqueType::DENOISER => &self.denoisers,
+            TechniqueType::SAMPLER => &self.samplers,
+        };
+        map.iter()
+            .filter(|(_, e)| !e.history.is_empty())
+            .map(|(id, e)| (id, &e.name, &e.history))
+    }
+
+    /// Returns the docker image a workspace was built with.
+    pub fn workspace_docker_image(&self, id: &str, uuid: &str) -> Option<&String> {
+        let (_, entry) = self.get_entry(id)?;
+        let workspace = entry.workspaces.iter().find(|w| w.uuid == uuid)?;
+        Some(&workspace.docker_image)
+    }
+
+    /// Returns the commit a workspace was built from.
+    pub fn workspace_commit_sha(&self, id: &str, uuid: &str) -> Option<&String> {
+        let (_, entry) = self.get_entry(id)?;
+        let workspace = entry.workspaces.iter().find(|w| w.uuid == uuid)?;
+        Some(&workspace.commit_sha)
+    }
+
+    /// Returns the benchmark suite a workspace was run with (None if it was run with all scenes).
+    pub fn workspace_suite(&self, id: &str, uuid: &str) -> Option<&String> {
+        let (_, entry) = self.get_entry(id)?;
+        let workspace = entry.workspaces.iter().find(|w| w.uuid == uuid)?;
+        workspace.suite.as_ref()
+    }
+
     pub fn remove_workspace(&mut self, id: &str, uuid: &str) -> Result<()> {
         let entry = match self.get_entry_mut(id) {
             Some((_, entry)) => entry,
@@ -403,3 +532,43 @@ impl Registry {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_history() {
+        let mut reg: Registry = serde_json::from_value(serde_json::json!({
+            "denoisers": {
+                "12": {
+                    "name": "A",
+                    "workspaces": [{
+                        "uuid": "u1",
+                        "commit_sha": "abcd1234",
+                        "docker_image": "fbksd-cuda",
+                        "status": {"Published": ["2020-01-01T00:00:00Z", "2020-01-02T00:00:00Z"]},
+                        "creation_time": "2020-01-01T00:00:00Z",
+                    }],
+                },
+            },
+            "samplers": {},
+        }))
+        .unwrap();
+        let mut summary = Summary::new();
+        summary.entry(4).or_default().insert(String::from("MSE"), 0.5);
+
+        reg.add_history("12", "u1", HistoryEvent::Published, 2, summary.clone()).unwrap();
+        // unchanged results
+        reg.add_history("12", "u1", HistoryEvent::ResultsUpdated, 2, summary.clone()).unwrap();
+        assert_eq!(reg.get_history(&TechniqueType::DENOISER).next().unwrap().2.len(), 1);
+
+        reg.add_history("12", "u1", HistoryEvent::ResultsUpdated, 3, summary.clone()).unwrap();
+        summary.get_mut(&4).unwrap().insert(String::from("MSE"), 0.4);
+        reg.add_history("12", "u1", HistoryEvent::ResultsUpdated, 3, summary.clone()).unwrap();
+        reg.add_history("12", "u1", HistoryEvent::ResultsUpdated, 3, summary).unwrap();
+        let history = reg.get_history(&TechniqueType::DENOISER).next().unwrap().2;
+        assert_eq!(history.len(), 3);
+        assert!(history.iter().skip(1).all(|r| r.event == HistoryEvent::ResultsUpdated));
+    }
+}
@@ -0,0 +1,427 @@
+//! Self-contained comparison reports.
+//!
+//! A report compares the results of a technique's workspace against the published techniques of the same group.
+//! It contains metric tables per scene and spp, aggregate rankings and embedded thumbnails, so it can be shared as a
+//! single file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+impl Format {
+    /// File extension used for reports in this format.
+    pub fn extension(&self) -> &str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(Format::Html),
+            "md" | "markdown" => Ok(Format::Markdown),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct Metric {
+    pub acronym: String,
+    pub name: String,
+    pub lower_is_better: bool,
+}
+
+/// Results of one technique for a scene and spp.
+pub struct Row {
+    pub technique: String,
+    pub aborted: bool,
+    pub metrics: HashMap<String, f32>,
+}
+
+/// Metrics of all techniques for a scene and spp.
+pub struct Table {
+    pub scene: String,
+    pub renderer: String,
+    pub spp: i32,
+    /// Retired scenes are not included in the rankings.
+    pub retired: bool,
+    /// Thumbnail of the reference image.
+    pub reference_thumb: Option<PathBuf>,
+    /// Thumbnail of the subject technique's result.
+    pub result_thumb: Option<PathBuf>,
+    pub rows: Vec<Row>,
+}
+
+/// Mean rank of each technique for a metric and spp (best first).
+pub struct Ranking {
+    pub metric: String,
+    pub spp: i32,
+    pub ranks: Vec<(String, f32)>,
+}
+
+pub struct Report {
+    pub technique: String,
+    pub uuid: String,
+    pub group: String,
+    /// Benchmark suite the workspace was run with.
+    pub suite: Option<String>,
+    pub metrics: Vec<Metric>,
+    pub tables: Vec<Table>,
+}
+
+impl Report {
+    /// Computes the mean rank of every technique, over all non-retired scenes, for each metric and spp.
+    ///
+    /// Aborted results and non-finite metric values are not ranked.
+    pub fn rankings(&self) -> Vec<Ranking> {
+        let mut rankings = Vec::new();
+        let mut spps: Vec<i32> = self.tables.iter().map(|t| t.spp).collect();
+        spps.sort();
+        spps.dedup();
+        for m in &self.metrics {
+            for spp in &spps {
+                let mut ranks: BTreeMap<&str, (f32, i32)> = BTreeMap::new();
+                for table in self.tables.iter().filter(|t| t.spp == *spp && !t.retired) {
+                    let mut values: Vec<(&str, f32)> = table
+                        .rows
+                        .iter()
+                        .filter(|r| !r.aborted)
+                        .filter_map(|r| r.metrics.get(&m.acronym).map(|v| (r.technique.as_str(), *v)))
+                        .filter(|(_, v)| v.is_finite())
+                        .collect();
+                    values.sort_by(|a, b| a.1.total_cmp(&b.1));
+                    if !m.lower_is_better {
+                        values.reverse();
+                    }
+                    for (i, (tech, _)) in values.iter().enumerate() {
+                        let rank = ranks.entry(tech).or_insert((0.0, 0));
+                        rank.0 += (i + 1) as f32;
+                        rank.1 += 1;
+                    }
+                }
+                if ranks.is_empty() {
+                    continue;
+                }
+                let mut ranks: Vec<(String, f32)> = ranks
+                    .into_iter()
+                    .map(|(tech, (sum, count))| (String::from(tech), sum / count as f32))
+                    .collect();
+                ranks.sort_by(|a, b| a.1.total_cmp(&b.1));
+                rankings.push(Ranking {
+                    metric: m.acronym.clone(),
+                    spp: *spp,
+                    ranks,
+                });
+            }
+        }
+        rankings
+    }
+
+    /// Renders the report in the given format.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Html => self.render_html(),
+            Format::Markdown => self.render_markdown(),
+        }
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# {} comparison report\n", escape_md(&self.technique)).unwrap();
+        match &self.suite {
+            Some(suite) => writeln!(
+                out,
+                "Workspace `{}` ({}, suite {}).\n",
+                self.uuid,
+                self.group,
+                escape_md(suite)
+            ),
+            None => writeln!(out, "Workspace `{}` ({}).\n", self.uuid, self.group),
+        }
+        .unwrap();
+
+        writeln!(out, "## Rankings\n").unwrap();
+        for r in self.rankings() {
+            writeln!(out, "### {} ({} spp)\n", escape_md(&r.metric), r.spp).unwrap();
+            writeln!(out, "| # | Technique | Mean rank |").unwrap();
+            writeln!(out, "|---|---|---|").unwrap();
+            for (i, (tech, rank)) in r.ranks.iter().enumerate() {
+                writeln!(out, "| {} | {} | {:.2} |", i + 1, self.emphasize_md(tech), rank).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        writeln!(out, "## Scenes\n").unwrap();
+        for t in &self.tables {
+            writeln!(
+                out,
+                "### {} ({}, {} spp)\n",
+                escape_md(&t.scene),
+                escape_md(&t.renderer),
+                t.spp
+            )
+            .unwrap();
+            let thumbs: Vec<String> = vec![&t.reference_thumb, &t.result_thumb]
+                .into_iter()
+                .filter_map(|thumb| thumb.as_ref().and_then(|p| data_uri(p)))
+                .map(|uri| format!("![]({})", uri))
+                .collect();
+            if !thumbs.is_empty() {
+                writeln!(out, "{}\n", thumbs.join(" ")).unwrap();
+            }
+            write!(out, "| Technique |").unwrap();
+            for m in &self.metrics {
+                write!(out, " {} |", escape_md(&m.acronym)).unwrap();
+            }
+            writeln!(out).unwrap();
+            writeln!(out, "|---|{}", "---|".repeat(self.metrics.len())).unwrap();
+            for row in &t.rows {
+                write!(out, "| {} |", self.emphasize_md(&row.technique)).unwrap();
+                for m in &self.metrics {
+                    write!(out, " {} |", format_value(row, &m.acronym)).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+
+    fn render_html(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">").unwrap();
+        writeln!(out, "<title>{} comparison report</title>", escape(&self.technique)).unwrap();
+        writeln!(
+            out,
+            "<style>table {{border-collapse: collapse;}} td, th {{border: 1px solid #ccc; padding: 4px 8px;}} \
+             .subject {{font-weight: bold;}}</style>\n</head>\n<body>"
+        )
+        .unwrap();
+        writeln!(out, "<h1>{} comparison report</h1>", escape(&self.technique)).unwrap();
+        match &self.suite {
+            Some(suite) => writeln!(
+                out,
+                "<p>Workspace <code>{}</code> ({}, suite <code>{}</code>).</p>",
+                self.uuid,
+                self.group,
+                escape(suite)
+            ),
+            None => writeln!(out, "<p>Workspace <code>{}</code> ({}).</p>", self.uuid, self.group),
+        }
+        .unwrap();
+
+        writeln!(out, "<h2>Rankings</h2>").unwrap();
+        for r in self.rankings() {
+            writeln!(out, "<h3>{} ({} spp)</h3>", escape(&r.metric), r.spp).unwrap();
+            writeln!(out, "<table>\n<tr><th>#</th><th>Technique</th><th>Mean rank</th></tr>").unwrap();
+            for (i, (tech, rank)) in r.ranks.iter().enumerate() {
+                writeln!(
+                    out,
+                    "<tr{}><td>{}</td><td>{}</td><td>{:.2}</td></tr>",
+                    self.subject_class(tech),
+                    i + 1,
+                    escape(tech),
+                    rank
+                )
+                .unwrap();
+            }
+            writeln!(out, "</table>").unwrap();
+        }
+
+        writeln!(out, "<h2>Scenes</h2>").unwrap();
+        for t in &self.tables {
+            writeln!(
+                out,
+                "<h3>{} ({}, {} spp)</h3>",
+                escape(&t.scene),
+                escape(&t.renderer),
+                t.spp
+            )
+            .unwrap();
+            for thumb in vec![&t.reference_thumb, &t.result_thumb] {
+                if let Some(uri) = thumb.as_ref().and_then(|p| data_uri(p)) {
+                    writeln!(out, "<img src=\"{}\">", uri).unwrap();
+                }
+            }
+            write!(out, "<table>\n<tr><th>Technique</th>").unwrap();
+            for m in &self.metrics {
+                write!(out, "<th title=\"{}\">{}</th>", escape(&m.name), escape(&m.acronym)).unwrap();
+            }
+            writeln!(out, "</tr>").unwrap();
+            for row in &t.rows {
+                write!(
+                    out,
+                    "<tr{}><td>{}</td>",
+                    self.subject_class(&row.technique),
+                    escape(&row.technique)
+                )
+                .unwrap();
+                for m in &self.metrics {
+                    write!(out, "<td>{}</td>", format_value(row, &m.acronym)).unwrap();
+                }
+                writeln!(out, "</tr>").unwrap();
+            }
+            writeln!(out, "</table>").unwrap();
+        }
+        writeln!(out, "</body>\n</html>").unwrap();
+        out
+    }
+
+    fn emphasize_md(&self, tech: &str) -> String {
+        if tech == self.technique {
+            format!("**{}**", escape_md(tech))
+        } else {
+            escape_md(tech)
+        }
+    }
+
+    fn subject_class(&self, tech: &str) -> &str {
+        if tech == self.technique {
+            " class=\"subject\""
+        } else {
+            ""
+        }
+    }
+}
+
+fn format_value(row: &Row, metric: &str) -> String {
+    if row.aborted {
+        return String::from("aborted");
+    }
+    match row.metrics.get(metric) {
+        Some(v) => format!("{:.5}", v),
+        None => String::from("-"),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes the characters with a meaning in Markdown (including table cell separators).
+fn escape_md(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_{}[]<>()#+-.!|~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns the image file encoded as a data uri, or None if it can't be read.
+fn data_uri(path: &PathBuf) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    let mime = match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => "image/png",
+        _ => "image/jpeg",
+    };
+    Some(format!("data:{};base64,{}", mime, base64::encode(&data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(technique: &str, value: f32) -> Row {
+        let mut metrics = HashMap::new();
+        metrics.insert(String::from("MSE"), value);
+        Row {
+            technique: String::from(technique),
+            aborted: false,
+            metrics,
+        }
+    }
+
+    fn table(scene: &str, rows: Vec<Row>) -> Table {
+        Table {
+            scene: String::from(scene),
+            renderer: String::from("pbrt-v3"),
+            spp: 4,
+            retired: false,
+            reference_thumb: None,
+            result_thumb: None,
+            rows,
+        }
+    }
+
+    #[test]
+    fn test_rankings() {
+        let report = Report {
+            technique: String::from("A"),
+            uuid: String::from("uuid"),
+            group: String::from("denoisers"),
+            suite: None,
+            metrics: vec![Metric {
+                acronym: String::from("MSE"),
+                name: String::from("Mean Squared Error"),
+                lower_is_better: true,
+            }],
+            tables: vec![
+                table("s1", vec![row("A", 0.1), row("B", 0.2)]),
+                table("s2", vec![row("A", 0.3), row("B", 0.2)]),
+                table("s3", vec![row("A", 0.1), row("B", 0.4)]),
+            ],
+        };
+        let rankings = report.rankings();
+        assert_eq!(rankings.len(), 1);
+        assert_eq!(rankings[0].ranks[0].0, "A");
+        assert!((rankings[0].ranks[0].1 - 4.0 / 3.0).abs() < 1e-6);
+        assert!(report.render(Format::Markdown).contains("| **A** |"));
+    }
+
+    #[test]
+    fn test_rankings_non_finite() {
+        let report = Report {
+            technique: String::from("A"),
+            uuid: String::from("uuid"),
+            group: String::from("denoisers"),
+            suite: None,
+            metrics: vec![Metric {
+                acronym: String::from("MSE"),
+                name: String::from("Mean Squared Error"),
+                lower_is_better: true,
+            }],
+            tables: vec![
+                table("s1", vec![row("A", f32::NAN), row("B", 0.2)]),
+                table("s2", vec![row("A", 0.1), row("B", f32::INFINITY)]),
+            ],
+        };
+        let rankings = report.rankings();
+        assert_eq!(rankings[0].ranks, vec![(String::from("A"), 1.0), (String::from("B"), 1.0)]);
+    }
+
+    #[test]
+    fn test_markdown_escape() {
+        let report = Report {
+            technique: String::from("my_tech|2"),
+            uuid: String::from("uuid"),
+            group: String::from("denoisers"),
+            suite: None,
+            metrics: Vec::new(),
+            tables: vec![table("*scene*", vec![row("my_tech|2", 0.1)])],
+        };
+        let md = report.render(Format::Markdown);
+        assert!(md.contains("# my\\_tech\\|2 comparison report"));
+        assert!(md.contains("### \\*scene\\* (pbrt\\-v3, 4 spp)"));
+        assert!(md.contains("| **my\\_tech\\|2** |"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Runtimes used to execute the benchmark.
+//!
+//! The benchmark (`fbksd run`, `fbksd results compute`) runs technique code, so it's normally executed inside a
+//! container (see the `docker` module). The `Local` runtime runs it directly on the host, which is useful for
+//! testing the whole flow on machines without docker or GPUs.
+//! The runtime and its resource limits are chosen in the system configuration.
+
+use crate::docker::{Docker, Podman};
+use crate::system_config::{Limits, RuntimeKind, SystemConfig};
+use std::io::{self, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How a run ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Exited(ExitStatus),
+    /// The run was killed after reaching the timeout.
+    TimedOut,
+}
+
+impl Outcome {
+    /// Returns true if the run exited successfully.
+    pub fn success(&self) -> bool {
+        match self {
+            Outcome::Exited(status) => status.success(),
+            Outcome::TimedOut => false,
+        }
+    }
+}
+
+/// A way of executing commands in a benchmark workspace.
+pub trait Runtime {
+    /// Runs `command` with `args` inside the `workspace` folder.
+    ///
+    /// Container runtimes use a container from `image`, other runtimes ignore it.
+    fn run(&self, image: &str, workspace: &Path, command: &str, args: &[&str]) -> io::Result<Outcome>;
+}
+
+/// Waits for the child process, calling `kill` if it doesn't finish before the timeout.
+pub fn wait_timeout<F>(mut child: Child, timeout: Option<u64>, kill: F) -> io::Result<Outcome>
+where
+    F: FnOnce(&mut Child) -> io::Result<()>,
+{
+    let timeout = match timeout {
+        Some(timeout) => Duration::from_secs(timeout),
+        None => return child.wait().map(Outcome::Exited),
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Outcome::Exited(status));
+        }
+        if start.elapsed() >= timeout {
+            log::warn!("run killed after {} s", timeout.as_secs());
+            kill(&mut child)?;
+            child.wait()?;
+            return Ok(Outcome::TimedOut);
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Spawns the command in a new process group, so it can be killed together with its children (see `kill_group()`).
+pub fn spawn_group(cmd: &mut Command) -> io::Result<Child> {
+    cmd.process_group(0).spawn()
+}
+
+/// Kills the process group of a child spawned with `spawn_group()`.
+pub fn kill_group(child: &mut Child) -> io::Result<()> {
+    let status = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .status()?;
+    if !status.success() {
+        return child.kill();
+    }
+    Ok(())
+}
+
+/// Output of a command, truncated to a maximum size.
+#[derive(Debug, Default)]
+pub struct CapturedOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Copies everything from `src` to `echo`, keeping up to `max_size` bytes.
+///
+/// If the output is longer, a note with the number of discarded bytes is added to the kept bytes.
+fn tee_capped<R: Read, W: Write>(mut src: R, mut echo: W, max_size: usize) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let mut discarded = 0;
+    let mut buf = [0; 8192];
+    loop {
+        let n = match src.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let _ = echo.write_all(&buf[..n]);
+        let keep = n.min(max_size - kept.len());
+        kept.extend_from_slice(&buf[..keep]);
+        discarded += n - keep;
+    }
+    if discarded > 0 {
+        kept.extend(format!("\n[{} bytes discarded]\n", discarded).into_bytes());
+    }
+    kept
+}
+
+/// Runs the command, echoing its stdout and stderr while keeping up to `max_size` bytes of each.
+pub fn run_captured(cmd: &mut Command, max_size: usize) -> io::Result<(ExitStatus, CapturedOutput)> {
+    run_captured_to(cmd, max_size, io::stdout(), io::stderr())
+}
+
+/// Same as `run_captured()`, but echoing the command stdout and stderr to the given writers.
+pub fn run_captured_to<O, E>(
+    cmd: &mut Command,
+    max_size: usize,
+    out_echo: O,
+    err_echo: E,
+) -> io::Result<(ExitStatus, CapturedOutput)>
+where
+    O: Write + Send + 'static,
+    E: Write + Send + 'static,
+{
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let out = thread::spawn(move || tee_capped(stdout, out_echo, max_size));
+    let err = thread::spawn(move || tee_capped(stderr, err_echo, max_size));
+    let status = child.wait()?;
+    let output = CapturedOutput {
+        stdout: out.join().expect("Failed to capture stdout"),
+        stderr: err.join().expect("Failed to capture stderr"),
+    };
+    Ok((status, output))
+}
+
+/// Runs the commands directly on the host, without any isolation.
+///
+/// Only the timeout limit is applied: the whole process group of the run is killed when it's reached.
+/// Expects the commands (e.g. `fbksd`) in the PATH.
+pub struct Local {
+    pub timeout: Option<u64>,
+}
+
+impl Runtime for Local {
+    fn run(&self, _image: &str, workspace: &Path, command: &str, args: &[&str]) -> io::Result<Outcome> {
+        let child = spawn_group(Command::new(command).args(args).current_dir(workspace))?;
+        wait_timeout(child, self.timeout, kill_group)
+    }
+}
+
+/// Returns the runtime of the given kind, applying the given limits.
+///
+/// The local runtime only applies the timeout, a warning is printed if other limits are set.
+pub fn new(kind: RuntimeKind, limits: Limits) -> Box<dyn Runtime> {
+    match kind {
+        RuntimeKind::Docker => Box::new(Docker {
+            nvidia: false,
+            limits,
+        }),
+        RuntimeKind::DockerNvidia => Box::new(Docker {
+            nvidia: true,
+            limits,
+        }),
+        RuntimeKind::Podman => Box::new(Podman { limits }),
+        RuntimeKind::Local => {
+            if limits.cpus.is_some() || limits.memory.is_some() || limits.pids.is_some() {
+                eprintln!("warning: the local runtime ignores the cpus, memory and pids limits");
+            }
+            Box::new(Local {
+                timeout: limits.timeout,
+            })
+        }
+    }
+}
+
+/// Returns the runtime chosen in the system configuration.
+pub fn from_config() -> Box<dyn Runtime> {
+    let config = SystemConfig::load();
+    new(config.runtime, config.limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_run() {
+        let dir = std::env::temp_dir();
+        let local = Local { timeout: Some(1) };
+        assert!(local.run("", &dir, "true", &[]).unwrap().success());
+        assert!(!local.run("", &dir, "false", &[]).unwrap().success());
+        match local.run("", &dir, "sleep", &["10"]).unwrap() {
+            Outcome::TimedOut => {}
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    fn is_running(pid: &str) -> bool {
+        // killed children of the run may stay as zombies until reaped by init
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => stat.split_whitespace().nth(2) != Some("Z"),
+            Err(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_local_run_kills_children() {
+        let dir = std::env::temp_dir().join("fbksd-test-local-run-kill");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let local = Local { timeout: Some(1) };
+        let script = "sleep 30 & echo $! > sleep.pid; wait";
+        match local.run("", &dir, "sh", &["-c", script]).unwrap() {
+            Outcome::TimedOut => {}
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+        let pid = std::fs::read_to_string(dir.join("sleep.pid")).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(!is_running(pid.trim()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_captured() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo abc; echo 1234567 >&2"]);
+        let (status, output) = run_captured_to(&mut cmd, 5, io::sink(), io::sink()).unwrap();
+        assert!(status.success());
+        assert_eq!(output.stdout, b"abc\n");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "12345\n[3 bytes discarded]\n");
+    }
+}
@@ -0,0 +1,96 @@
+//! Versioned benchmark suites.
+//!
+//! A suite is a named and versioned set of scenes (e.g. "fbksd v3"), so results can be cited against a fixed
+//! benchmark while new scenes keep being added. Each suite is defined in a json file in the suites folder
+//! (see `paths::suites_path()`):
+//!
+//! ```json
+//! {
+//!   "name": "fbksd",
+//!   "version": 3,
+//!   "description": "Scenes used in the fbksd paper.",
+//!   "scenes": ["Sponza", "Living Room"]
+//! }
+//! ```
+
+use crate::paths;
+use glob::glob;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Suite {
+    pub name: String,
+    pub version: u32,
+    #[serde(default)]
+    pub description: String,
+    pub scenes: Vec<String>,
+}
+
+impl Suite {
+    /// Identifier of the suite version (`<name>-v<version>`).
+    pub fn id(&self) -> String {
+        format!("{}-v{}", self.name, self.version)
+    }
+
+    /// Returns the set of scenes in the suite.
+    pub fn scene_set(&self) -> HashSet<String> {
+        self.scenes.iter().cloned().collect()
+    }
+}
+
+/// Loads all suites, sorted by name and version.
+///
+/// Invalid suite files are reported and skipped.
+pub fn load_all() -> Vec<Suite> {
+    load_dir(&paths::suites_path())
+}
+
+/// Loads all suites from the json files in the given folder (see `load_all()`).
+fn load_dir(dir: &Path) -> Vec<Suite> {
+    let pattern = dir.join("*.json");
+    let mut suites: Vec<Suite> = glob(pattern.to_str().unwrap())
+        .expect("Failed to read glob pattern")
+        .filter_map(|e| e.ok())
+        .filter_map(|path| match load_file(&path) {
+            Ok(suite) => Some(suite),
+            Err(err) => {
+                eprintln!("invalid suite file {:?} skipped: {}", path, err);
+                None
+            }
+        })
+        .collect();
+    suites.sort_by(|a, b| a.name.cmp(&b.name).then(a.version.cmp(&b.version)));
+    suites
+}
+
+fn load_file(path: &Path) -> Result<Suite, String> {
+    let data = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&data).map_err(|err| err.to_string())
+}
+
+/// Finds a suite by its id (see `Suite::id()`).
+pub fn find(id: &str) -> Option<Suite> {
+    load_all().into_iter().find(|s| s.id() == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_dir_skips_invalid() {
+        let dir = std::env::temp_dir().join("fbksd-test-suites");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("fbksd-v2.json"), r#"{"name": "fbksd", "version": 2, "scenes": ["Sponza"]}"#).unwrap();
+        fs::write(dir.join("fbksd-v1.json"), r#"{"name": "fbksd", "version": 1, "scenes": []}"#).unwrap();
+        fs::write(dir.join("broken.json"), r#"{"name": "broken", "scenes": ["#).unwrap();
+        fs::write(dir.join("no-version.json"), r#"{"name": "other", "scenes": []}"#).unwrap();
+        let ids: Vec<String> = load_dir(&dir).iter().map(|s| s.id()).collect();
+        assert_eq!(ids, ["fbksd-v1", "fbksd-v2"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
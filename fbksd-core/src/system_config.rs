@@ -7,6 +7,7 @@ use crate::paths;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::str::FromStr;
 
 /// Server system configurations.
 #[derive(Debug, Deserialize, Serialize)]
@@ -19,6 +20,223 @@ pub struct SystemConfig {
     pub spps: Vec<i32>,
     /// Map of docker images available. The key is the alias for an image.
     pub configs: HashMap<String, String>,
+    /// How EXR images are converted to web images.
+    #[serde(default)]
+    pub images: ImagesConfig,
+    /// Runtime used to execute the benchmark.
+    #[serde(default)]
+    pub runtime: RuntimeKind,
+    /// Resource limits applied to the benchmark runs.
+    #[serde(default)]
+    pub limits: Limits,
+    /// How runs are split into shards executed in parallel.
+    #[serde(default)]
+    pub parallel: ParallelConfig,
+    /// How the techniques output is kept.
+    #[serde(default)]
+    pub logs: LogsConfig,
+    /// What the techniques build settings are allowed to change.
+    #[serde(default)]
+    pub build: BuildPolicy,
+}
+
+/// Limits of the techniques build settings (the `build` section of their info.json files).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BuildPolicy {
+    /// cmake definitions techniques may set. Names ending with `*` allow any name with that prefix.
+    #[serde(default = "BuildPolicy::default_allowed_definitions")]
+    pub allowed_definitions: Vec<String>,
+    /// Maximum number of parallel build jobs.
+    #[serde(default = "BuildPolicy::default_max_jobs")]
+    pub max_jobs: usize,
+    /// Maximum build time, in seconds.
+    #[serde(default = "BuildPolicy::default_max_timeout")]
+    pub max_timeout: u64,
+}
+
+impl BuildPolicy {
+    fn default_allowed_definitions() -> Vec<String> {
+        vec![String::from("CMAKE_CUDA_ARCHITECTURES")]
+    }
+
+    fn default_max_jobs() -> usize {
+        8
+    }
+
+    fn default_max_timeout() -> u64 {
+        2 * 60 * 60
+    }
+
+    /// Returns true if techniques may set the given cmake definition.
+    pub fn allows_definition(&self, name: &str) -> bool {
+        self.allowed_definitions.iter().any(|allowed| match allowed.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == allowed,
+        })
+    }
+}
+
+impl Default for BuildPolicy {
+    fn default() -> Self {
+        BuildPolicy {
+            allowed_definitions: Self::default_allowed_definitions(),
+            max_jobs: Self::default_max_jobs(),
+            max_timeout: Self::default_max_timeout(),
+        }
+    }
+}
+
+/// Run logs configuration.
+///
+/// The stdout and stderr of each technique run are saved with its results (`<spp>_0_stdout.log`,
+/// `<spp>_0_stderr.log`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LogsConfig {
+    /// Maximum size of each log file, in bytes (the rest of the output is discarded).
+    #[serde(default = "LogsConfig::default_max_size")]
+    pub max_size: usize,
+}
+
+impl LogsConfig {
+    fn default_max_size() -> usize {
+        1 << 20
+    }
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        LogsConfig {
+            max_size: Self::default_max_size(),
+        }
+    }
+}
+
+/// How the work of a run is split into shards.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum ShardBy {
+    /// Each shard runs all techniques with a subset of the scenes.
+    Scene,
+    /// Each shard runs a subset of the techniques with all scenes.
+    Technique,
+}
+
+impl FromStr for ShardBy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scene" => Ok(ShardBy::Scene),
+            "technique" => Ok(ShardBy::Technique),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parallel execution configuration.
+///
+/// Each shard runs in its own temporary workspace (and container), with the resource limits applied to each of them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ParallelConfig {
+    /// Maximum number of shards running at the same time.
+    #[serde(default = "ParallelConfig::default_workers")]
+    pub workers: usize,
+    #[serde(default = "ParallelConfig::default_shard_by")]
+    pub shard_by: ShardBy,
+}
+
+impl ParallelConfig {
+    fn default_workers() -> usize {
+        1
+    }
+
+    fn default_shard_by() -> ShardBy {
+        ShardBy::Scene
+    }
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        ParallelConfig {
+            workers: Self::default_workers(),
+            shard_by: Self::default_shard_by(),
+        }
+    }
+}
+
+/// Resource limits of a benchmark run.
+///
+/// Container runtimes apply all limits, the local runtime only the timeout.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Limits {
+    /// Number of CPUs (e.g. `4.5`).
+    #[serde(default)]
+    pub cpus: Option<f32>,
+    /// Memory limit, in the docker format (e.g. `"16g"`).
+    #[serde(default)]
+    pub memory: Option<String>,
+    /// Maximum number of processes.
+    #[serde(default)]
+    pub pids: Option<u32>,
+    /// Wall-clock timeout of each run, in seconds.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Disables network access in the containers (`--network=none`).
+    #[serde(default)]
+    pub no_network: bool,
+}
+
+/// Runtime used to execute the benchmark (see the `runtime` module).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum RuntimeKind {
+    /// Docker containers.
+    Docker,
+    /// Docker containers using the nvidia runtime (GPU access).
+    DockerNvidia,
+    /// Podman containers.
+    Podman,
+    /// Directly on the host, without containers.
+    Local,
+}
+
+impl Default for RuntimeKind {
+    fn default() -> Self {
+        RuntimeKind::DockerNvidia
+    }
+}
+
+/// Tone mapping operator applied when converting EXR images to web images.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Values are just clamped to [0, 1].
+    Clamp,
+    /// Reinhard operator (`x / (1 + x)`).
+    Reinhard,
+}
+
+/// Web images conversion configuration.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ImagesConfig {
+    /// Tone mapping operator.
+    #[serde(default = "ImagesConfig::default_tone_map")]
+    pub tone_map: ToneMap,
+    /// Exposure in stops (the image is scaled by `2^exposure` before tone mapping).
+    #[serde(default)]
+    pub exposure: f32,
+}
+
+impl ImagesConfig {
+    fn default_tone_map() -> ToneMap {
+        ToneMap::Clamp
+    }
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            tone_map: Self::default_tone_map(),
+            exposure: 0.0,
+        }
+    }
 }
 
 impl SystemConfig {
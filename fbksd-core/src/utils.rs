@@ -1,8 +1,12 @@
 use crate::paths;
 use fs2::FileExt;
+use glob::glob;
+use std::collections::HashSet;
 use std::env;
 use std::fs::File;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 pub struct CD {
     prev: PathBuf,
@@ -151,6 +155,72 @@ pub fn relative_from(path: &Path, from: &Path) -> Option<PathBuf> {
     }
 }
 
+/// Runs `sha256sum` with the given arguments and returns the hashes (in the same order as the files).
+fn sha256sum_cmd(args: &[&str], stdin: Option<&str>) -> io::Result<Vec<String>> {
+    let mut child = Command::new("sha256sum")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(data) = stdin {
+        child.stdin.as_mut().unwrap().write_all(data.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "sha256sum failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|l| l.split_whitespace().next().map(String::from))
+        .collect())
+}
+
+/// Maximum number of files hashed by a single `sha256sum` call (to stay below the arguments size limit).
+const SHA256_BATCH_SIZE: usize = 256;
+
+/// Returns the sha256 hash of a file, or of all files inside a directory (recursively).
+///
+/// The hash of a directory changes if any file inside it is changed, added, removed or renamed.
+/// Expects `sha256sum` in the PATH.
+pub fn sha256(path: &Path) -> io::Result<String> {
+    if !path.is_dir() {
+        let hashes = sha256sum_cmd(&[path.to_str().unwrap()], None)?;
+        return hashes
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "sha256sum failed"));
+    }
+    sha256_dir(path, &[])
+}
+
+/// Returns the sha256 hash of all files inside a directory (recursively), except the excluded ones.
+pub fn sha256_dir(path: &Path, exclude: &[PathBuf]) -> io::Result<String> {
+    let exclude: HashSet<PathBuf> = exclude.iter().filter_map(|p| p.canonicalize().ok()).collect();
+    let pattern = path.join("**/*");
+    let mut files: Vec<PathBuf> = glob(pattern.to_str().unwrap())
+        .expect("Failed to read glob pattern")
+        .filter_map(|e| e.ok())
+        .filter(|p| p.is_file() && !p.canonicalize().is_ok_and(|p| exclude.contains(&p)))
+        .collect();
+    files.sort();
+    let mut listing = String::new();
+    for batch in files.chunks(SHA256_BATCH_SIZE) {
+        let names: Vec<&str> = batch.iter().map(|f| f.to_str().unwrap()).collect();
+        let hashes = sha256sum_cmd(&names, None)?;
+        for (hash, file) in hashes.iter().zip(batch) {
+            let rel = file.strip_prefix(path).unwrap();
+            listing.push_str(&format!("{}  {}\n", hash, rel.to_str().unwrap()));
+        }
+    }
+    sha256_str(&listing)
+}
+
+/// Returns the sha256 hash of the given data.
+pub fn sha256_str(data: &str) -> io::Result<String> {
+    let hashes = sha256sum_cmd(&["-"], Some(data))?;
+    Ok(hashes[0].clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,4 +237,27 @@ mod tests {
             );
         });
     }
+
+    #[test]
+    fn test_sha256_dir() {
+        let dir = env::temp_dir().join("fbksd-test-sha256-dir");
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(dir.join("textures")).unwrap();
+        // more files than a single sha256sum batch
+        for i in 0..SHA256_BATCH_SIZE + 10 {
+            std::fs::write(dir.join("textures").join(format!("{}.txt", i)), i.to_string()).unwrap();
+        }
+        std::fs::write(dir.join("ref.exr"), "ref").unwrap();
+        let exclude = vec![dir.join("ref.exr")];
+        let hash = sha256_dir(&dir, &exclude).unwrap();
+        assert_ne!(hash, sha256(&dir).unwrap());
+
+        std::fs::write(dir.join("ref.exr"), "new ref").unwrap();
+        assert_eq!(sha256_dir(&dir, &exclude).unwrap(), hash);
+        std::fs::write(dir.join("textures/5.txt"), "changed").unwrap();
+        assert_ne!(sha256_dir(&dir, &exclude).unwrap(), hash);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,9 +1,14 @@
 use crate::ci;
 use crate::config;
-use crate::system_config::SystemConfig;
+use crate::docker;
+use crate::images;
+use crate::manifest::{self, Manifest};
+use crate::system_config::{ShardBy, SystemConfig};
 use crate::page;
 use crate::paths;
 use crate::registry as reg;
+use crate::report;
+use crate::suites::{self, Suite};
 use crate::utils;
 use reg::TechniqueType;
 
@@ -12,7 +17,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::error;
 use std::fmt;
@@ -24,6 +29,8 @@ use std::process::{Command, Stdio};
 #[derive(Debug)]
 pub enum Error {
     UuidNotFound,
+    /// The scenes folder has invalid scene files.
+    InvalidScenes,
     Unspecified,
 }
 impl fmt::Display for Error {
@@ -31,6 +38,7 @@ impl fmt::Display for Error {
         use Error::*;
         match *self {
             UuidNotFound => "uuid not found for the given technique".fmt(f),
+            InvalidScenes => "invalid scenes found".fmt(f),
             Unspecified => "unspecified error".fmt(f),
         }
     }
@@ -38,7 +46,7 @@ impl fmt::Display for Error {
 impl error::Error for Error {}
 type WPResult<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct Scene {
     pub name: String,
     path: String,
@@ -47,18 +55,164 @@ pub struct Scene {
     ref_img: String,
     #[serde(rename = "ref", default)]
     citation: String,
+    /// Retired scenes are kept for existing results, but are not used in new runs or aggregates.
+    #[serde(default)]
+    pub retired: bool,
+    /// Categories of the scene (e.g. "volumes", "motion blur").
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Spps used for this scene instead of the system ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spps: Option<Vec<i32>>,
+    /// Spps used for this scene in addition to the system ones (or the ones in `spps`).
+    #[serde(rename = "extra-spps", default, skip_serializing_if = "Vec::is_empty")]
+    extra_spps: Vec<i32>,
+    /// Content hashes, computed when the scenes cache is updated.
+    #[serde(default)]
+    pub hashes: SceneHashes,
     #[serde(skip)]
     png: String,
     #[serde(skip)]
     thumb: String,
 }
 
+/// Content hashes of a scene (see `utils::sha256()`).
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SceneHashes {
+    /// Hash of the scene folder (the scene path if it's a folder, or the folder containing it, together with the scene
+    /// file), without the definition files, reference images and scene files of any scene.
+    pub scene: String,
+    /// Hash of the reference image.
+    pub reference: String,
+}
+
+impl Scene {
+    /// Returns the spps this scene should run with, given the system spps.
+    pub fn spps(&self, system_spps: &[i32]) -> Vec<i32> {
+        let mut spps = match &self.spps {
+            Some(spps) => spps.clone(),
+            None => Vec::from(system_spps),
+        };
+        spps.extend(&self.extra_spps);
+        spps.sort();
+        spps.dedup();
+        spps
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Renderer {
     pub renderer: String,
     pub scenes: Vec<Scene>,
 }
 
+/// Reads the scenes cache file (generated by `update_scenes()`).
+pub fn read_scenes_cache() -> Vec<Renderer> {
+    let data = fs::read_to_string(paths::scenes_cache_path()).expect("Failed reading the scenes file");
+    serde_json::from_str(&data).expect("Failed deserializing the scenes file")
+}
+
+/// Returns the names of all scenes in the cache that are not retired.
+pub fn active_scenes() -> HashSet<String> {
+    read_scenes_cache()
+        .iter()
+        .flat_map(|r| r.scenes.iter().filter(|s| !s.retired).map(|s| s.name.clone()))
+        .collect()
+}
+
+/// Returns the spps each active scene should run with (scene name -> spps).
+pub fn scenes_spps() -> HashMap<String, Vec<i32>> {
+    let system_spps = SystemConfig::load().spps;
+    read_scenes_cache()
+        .iter()
+        .flat_map(|r| r.scenes.iter().filter(|s| !s.retired))
+        .map(|s| (s.name.clone(), s.spps(&system_spps)))
+        .collect()
+}
+
+/// Selects active scenes and their spps (scene name -> spps).
+///
+/// Empty filters select everything: `names` filters the scenes by name, `renderer` by renderer, and `spps` the
+/// spps of each scene. Scenes left without spps are not included.
+pub fn select_scenes(names: &[String], renderer: Option<&str>, spps: &[i32]) -> BTreeMap<String, Vec<i32>> {
+    let system_spps = SystemConfig::load().spps;
+    read_scenes_cache()
+        .iter()
+        .filter(|r| renderer.map_or(true, |name| r.renderer == name))
+        .flat_map(|r| r.scenes.iter().filter(|s| !s.retired))
+        .filter(|s| names.is_empty() || names.contains(&s.name))
+        .map(|s| {
+            let scene_spps: Vec<i32> = s
+                .spps(&system_spps)
+                .into_iter()
+                .filter(|spp| spps.is_empty() || spps.contains(spp))
+                .collect();
+            (s.name.clone(), scene_spps)
+        })
+        .filter(|(_, spps)| !spps.is_empty())
+        .collect()
+}
+
+/// Differences between two versions of the scenes cache.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ScenesDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Scenes whose files changed.
+    pub changed: Vec<String>,
+}
+
+impl ScenesDiff {
+    pub fn new(old: &[Renderer], new: &[Renderer]) -> ScenesDiff {
+        fn flatten(renderers: &[Renderer]) -> BTreeMap<String, (&str, &Scene)> {
+            renderers
+                .iter()
+                .flat_map(|r| r.scenes.iter().map(move |s| (s.name.clone(), (r.renderer.as_str(), s))))
+                .collect()
+        }
+        let old = flatten(old);
+        let new = flatten(new);
+        let mut diff = ScenesDiff::default();
+        for (name, scene) in &new {
+            match old.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(old_scene) if old_scene != scene => diff.changed.push(name.clone()),
+                _ => {}
+            }
+        }
+        diff.removed = old.keys().filter(|name| !new.contains_key(*name)).cloned().collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for ScenesDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (sign, names) in vec![("+", &self.added), ("-", &self.removed), ("~", &self.changed)] {
+            for name in names {
+                writeln!(f, "{} {}", sign, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the scenes that were added to the cache but didn't run with the published techniques yet.
+pub fn read_pending_scenes() -> Vec<String> {
+    match fs::read_to_string(paths::scenes_pending_path()) {
+        Ok(data) => serde_json::from_str(&data).expect("Failed deserializing the pending scenes file"),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_pending_scenes(scenes: &[String]) {
+    let data = serde_json::to_string_pretty(scenes).expect("Failed to serialize pending scenes");
+    fs::write(paths::scenes_pending_path(), &data).expect("Failed to save pending scenes file");
+}
+
 #[derive(Debug, Deserialize)]
 struct Metric {
     acronym: String,
@@ -66,6 +220,9 @@ struct Metric {
     reference: String,
     lower_is_better: bool,
     has_error_map: bool,
+    /// Error value mapped to the top of the error maps false-color scale.
+    #[serde(default = "Metric::default_error_map_scale")]
+    error_map_scale: f32,
     command: String,
 }
 
@@ -76,6 +233,27 @@ impl Metric {
             serde_json::from_str(&data).expect("Failed deserializing the info json");
         metric
     }
+
+    fn default_error_map_scale() -> f32 {
+        1.0
+    }
+}
+
+/// Reads all metrics available in the iqa folder.
+fn read_metrics(path: &Path) -> Vec<Metric> {
+    let mut metrics = Vec::new();
+    if !path.is_dir() {
+        return metrics;
+    }
+    let pattern = path.join("*/info.json");
+    for entry in
+        glob(pattern.to_str().expect("Failed path to string")).expect("Failed to read glob pattern")
+    {
+        if let Ok(path) = entry {
+            metrics.push(Metric::read(path));
+        }
+    }
+    metrics
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,6 +272,12 @@ struct Result {
     id: i32,
     #[serde(skip)]
     metrics: HashMap<String, f32>,
+    /// Error maps images (metric acronym -> path relative to the technique results folder).
+    #[serde(skip)]
+    error_maps: HashMap<String, String>,
+    /// Run logs (`stdout` or `stderr` -> path relative to the technique results folder).
+    #[serde(skip)]
+    logs: HashMap<String, String>,
     #[serde(skip)]
     scene_name: String,
 }
@@ -108,6 +292,8 @@ impl Result {
         let mut res: Result = serde_json::from_str(&data).expect("Failed to deserialize log file");
         res.scene_name = String::from(scene_name);
         let spp = res.spp_budget;
+        let version = log.parent().unwrap().parent().unwrap().file_name().unwrap();
+        let version = String::from(version.to_str().unwrap());
         let _cd = utils::CD::new(&log.parent().unwrap());
         for entry in
             glob(format!("{}_0_*_value.json", spp).as_str()).expect("Failed to read glob pattern")
@@ -123,6 +309,27 @@ impl Result {
                     .insert(String::from(metric), *val.get(metric).unwrap());
             }
         }
+        for entry in
+            glob(format!("{}_0_*_map.exr", spp).as_str()).expect("Failed to read glob pattern")
+        {
+            if let Ok(path) = entry {
+                if let Some(metric) = images::error_map_metric(&path) {
+                    let png = PathBuf::from(&version)
+                        .join(scene_name)
+                        .join(images::png_path(&path));
+                    res.error_maps
+                        .insert(metric, String::from(png.to_str().unwrap()));
+                }
+            }
+        }
+        for stream in &["stdout", "stderr"] {
+            let log = format!("{}_0_{}.log", spp, stream);
+            if Path::new(&log).is_file() {
+                let path = PathBuf::from(&version).join(scene_name).join(&log);
+                res.logs
+                    .insert(String::from(*stream), String::from(path.to_str().unwrap()));
+            }
+        }
         res
     }
 }
@@ -138,6 +345,9 @@ struct Technique {
     pub results: Vec<Result>,
     #[serde(skip)]
     id: i32,
+    /// Loaded from an unpublished workspace (see `Workspace::load_technique()`).
+    #[serde(skip)]
+    private: bool,
 }
 
 impl Technique {
@@ -174,6 +384,93 @@ impl Technique {
     fn scenes(&self) -> HashSet<String> {
         self.results.iter().map(|x| x.scene_name.clone()).collect()
     }
+
+    /// Returns the expected (scene -> spps) results this technique doesn't have.
+    fn missing(&self, expected: &HashMap<String, Vec<i32>>) -> BTreeMap<String, Vec<i32>> {
+        let existing: HashSet<(&str, i32)> = self
+            .results
+            .iter()
+            .map(|r| (r.scene_name.as_str(), r.spp_budget))
+            .collect();
+        expected
+            .iter()
+            .filter_map(|(scene, spps)| {
+                let spps: Vec<i32> = spps
+                    .iter()
+                    .filter(|spp| !existing.contains(&(scene.as_str(), **spp)))
+                    .cloned()
+                    .collect();
+                if spps.is_empty() {
+                    None
+                } else {
+                    Some((scene.clone(), spps))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the (scene, spp) of the results this technique has, ignoring aborted results.
+    fn complete_results(&self) -> HashSet<(String, i32)> {
+        self.results
+            .iter()
+            .filter(|r| !r.aborted)
+            .map(|r| (r.scene_name.clone(), r.spp_budget))
+            .collect()
+    }
+
+    /// Returns the mean value of each metric per spp over the given scenes, ignoring aborted results.
+    fn summary(&self, scenes: &HashSet<String>) -> reg::Summary {
+        self.summary_of(|r| scenes.contains(&r.scene_name))
+    }
+
+    /// Returns the mean value of each metric per spp over the given (scene, spp) results, ignoring aborted results.
+    fn summary_over(&self, results: &HashSet<(String, i32)>) -> reg::Summary {
+        self.summary_of(|r| results.contains(&(r.scene_name.clone(), r.spp_budget)))
+    }
+
+    fn summary_of<F: Fn(&Result) -> bool>(&self, include: F) -> reg::Summary {
+        let mut sums: BTreeMap<i32, BTreeMap<String, (f32, i32)>> = BTreeMap::new();
+        for r in self.results.iter().filter(|r| !r.aborted && include(r)) {
+            let spp_sums = sums.entry(r.spp_budget).or_insert_with(BTreeMap::new);
+            for (metric, value) in &r.metrics {
+                let sum = spp_sums.entry(metric.clone()).or_insert((0.0, 0));
+                sum.0 += value;
+                sum.1 += 1;
+            }
+        }
+        sums.into_iter()
+            .map(|(spp, metrics)| {
+                let means = metrics
+                    .into_iter()
+                    .map(|(m, (sum, count))| (m, sum / count as f32))
+                    .collect();
+                (spp, means)
+            })
+            .collect()
+    }
+}
+
+/// Returns the (scene, spp) results all the given techniques have (see `Technique::complete_results()`).
+fn common_results(techs: &[Technique]) -> HashSet<(String, i32)> {
+    let mut techs = techs.iter();
+    let mut common = match techs.next() {
+        Some(t) => t.complete_results(),
+        None => return HashSet::new(),
+    };
+    for t in techs {
+        let results = t.complete_results();
+        common.retain(|r| results.contains(r));
+    }
+    common
+}
+
+/// Returns the number of scenes per spp of the given (scene, spp) results.
+fn count_scenes(results: &HashSet<(String, i32)>) -> BTreeMap<i32, usize> {
+    let mut counts = BTreeMap::new();
+    for (_, spp) in results {
+        *counts.entry(*spp).or_insert(0) += 1;
+    }
+    counts
 }
 
 #[derive(Debug)]
@@ -192,11 +489,8 @@ impl Workspace {
         for r in &mut self.renderers {
             for s in &mut r.scenes {
                 let exr = PathBuf::from(&r.renderer).join(&s.ref_img);
-                let thumb = exr.file_stem().unwrap();
-                let mut thumb = String::from(thumb.to_str().unwrap());
-                thumb.push_str("_thumb256.jpg");
-                let thumb = exr.with_file_name(thumb);
-                let png = exr.with_extension("png");
+                let thumb = images::thumb_path(&exr);
+                let png = images::png_path(&exr);
                 // all images are relative to the scenes root folder
                 s.ref_img = String::from(exr.to_str().unwrap());
                 s.png = String::from(png.to_str().unwrap());
@@ -206,25 +500,14 @@ impl Workspace {
     }
 
     fn load_metrics(&mut self, path: &Path) {
-        if !path.is_dir() {
-            return;
-        }
-        let _cd = utils::CD::new(&path);
-        let pattern = path.join("*/info.json");
-        for entry in glob(pattern.to_str().expect("Failed path to string"))
-            .expect("Failed to read glob pattern")
-        {
-            if let Ok(path) = entry {
-                self.metrics.push(Metric::read(path));
-            }
-        }
+        self.metrics = read_metrics(path);
     }
 
     fn load_denoisers(&mut self, path: &Path) {
         if !path.is_dir() {
             return;
         }
-        let re = Regex::new(r"workspaces/denoisers/(\d+)/published").unwrap();
+        let re = Regex::new(r"workspaces/denoisers/([^/]+)/published").unwrap();
         let _cd = utils::CD::new(&path);
         let pattern = path.join("*/published");
         for entry in glob(pattern.to_str().expect("Failed path to string"))
@@ -232,7 +515,7 @@ impl Workspace {
         {
             if let Ok(path) = entry {
                 let caps = re.captures(path.to_str().unwrap()).unwrap();
-                let id: i32 = caps.get(1).unwrap().as_str().parse().unwrap();
+                let id = page::technique_id(caps.get(1).unwrap().as_str());
                 let tech = Technique::read(id, path).unwrap();
                 self.denoisers.push(tech);
             }
@@ -245,35 +528,33 @@ impl Workspace {
         }
         let _cd = utils::CD::new(&path);
         let pattern = path.join("*/published/info.json");
-        let re = Regex::new(r"results/samplers/(\d+)/published/info.json$").unwrap();
+        let re = Regex::new(r"results/samplers/([^/]+)/published/info.json$").unwrap();
         for entry in glob(pattern.to_str().expect("Failed path to string"))
             .expect("Failed to read glob pattern")
         {
             if let Ok(path) = entry {
                 let caps = re.captures(path.to_str().unwrap()).unwrap();
-                let id: i32 = caps.get(1).unwrap().as_str().parse().unwrap();
+                let id = page::technique_id(caps.get(1).unwrap().as_str());
                 let tech = Technique::read(id, path).unwrap();
                 self.samplers.push(tech);
             }
         }
     }
 
+    /// Sets the results page ids, assigned to (group, technique id, scene name, spp) (see `page::PageIds`).
     fn update_indices(&mut self) {
-        let mut next_id = 0;
-        for f in &mut self.denoisers {
-            for r in &mut f.results {
-                r.id = next_id;
-                next_id += 1;
-            }
-        }
-
-        let mut next_id = 0;
-        for f in &mut self.samplers {
-            for r in &mut f.results {
-                r.id = next_id;
-                next_id += 1;
+        let mut page_ids = page::PageIds::load();
+        for (group, techs) in vec![
+            (TechniqueType::DENOISER, &mut self.denoisers),
+            (TechniqueType::SAMPLER, &mut self.samplers),
+        ] {
+            for f in techs {
+                for r in &mut f.results {
+                    r.id = page_ids.get(&page::result_key(group.as_str(), f.id, &r.scene_name, r.spp_budget));
+                }
             }
         }
+        page_ids.save();
     }
 
     /// Reads the registry and loads all published techniques
@@ -284,7 +565,7 @@ impl Workspace {
             samplers: Vec::new(),
             metrics: Vec::new(),
         };
-        wp.load_scenes(paths::scenes_path().join(".fbksd-scenes-cache.json"));
+        wp.load_scenes(paths::scenes_cache_path());
         wp.load_metrics(paths::iqa_path());
         wp.load_denoisers(paths::denoisers_workspaces_path());
         wp.load_samplers(paths::samplers_workspaces_path());
@@ -293,8 +574,14 @@ impl Workspace {
     }
 
     pub fn load_technique(&mut self, group: &TechniqueType, proj: &ci::ProjectInfo, uuid: String) {
-        let id: i32 = proj.id.parse().unwrap();
-        let tech = Technique::read(id, paths::tech_workspace_path(group, &proj.id, &uuid)).unwrap();
+        let id = page::technique_id(&proj.id);
+        let mut tech = Technique::read(id, paths::tech_workspace_path(group, &proj.id, &uuid)).unwrap();
+        tech.private = true;
+        self.insert_technique(group, tech);
+    }
+
+    fn insert_technique(&mut self, group: &TechniqueType, tech: Technique) {
+        let id = tech.id;
         let techs = match group {
             TechniqueType::DENOISER => &mut self.denoisers,
             TechniqueType::SAMPLER => &mut self.samplers,
@@ -311,58 +598,74 @@ impl Workspace {
         self.update_indices();
     }
 
-    /// Copy all scenes reference images (png and thumbnail) to the public scenes image folder.
+    /// Generates the web images (png and thumbnail) of all scenes reference images in the public scenes image folder.
+    ///
+    /// Images are regenerated only if the reference EXR changed since the last export, or if the conversion settings
+    /// changed (see `images::Stamp`).
     pub fn export_reference_images(&self) {
         let path = paths::public_page_path().join("scenes");
         if !path.is_dir() {
             fs::create_dir_all(&path)
                 .expect("Failed to create destination folder for scene images");
         }
+        let config = SystemConfig::load().images;
+        let mut stamp = images::Stamp::read(&path);
+        let config_changed = stamp.config_changed(&config);
         let src_scenes = paths::scenes_path();
         for r in &self.renderers {
             for s in &r.scenes {
-                let exr = path.join(&s.ref_img);
-                let dest_img_path = exr.parent().unwrap();
-                if !dest_img_path.is_dir() {
-                    fs::create_dir_all(dest_img_path)
-                        .expect("Failed to create destination folder for scene images");
+                let exr = src_scenes.join(&s.ref_img);
+                let png = path.join(&s.png);
+                let thumb = path.join(&s.thumb);
+                let converted = if config_changed {
+                    images::convert(&exr, &png, &thumb, &config)
+                } else {
+                    images::convert_if_changed(&exr, &png, &thumb, &config)
+                };
+                if !converted {
+                    panic!("Failed to export reference image for scene {}", s.name);
                 }
-                fs::copy(src_scenes.join(&s.png), path.join(&s.png))
-                    .expect("Failed to export png scene image");
-                fs::copy(src_scenes.join(&s.thumb), path.join(&s.thumb))
-                    .expect("Failed to export jpg scene image");
+                stamp.images.insert(PathBuf::from(&s.png));
+                stamp.images.insert(PathBuf::from(&s.thumb));
             }
         }
+        stamp.config = Some(config);
+        if !stamp.save(&path) {
+            panic!("Failed to save the reference images stamp");
+        }
     }
 
     /// saves the page data to the given page folder (not including images).
     pub fn export_page(&self, path: &Path) {
         let path = path.join("data");
         // scenes
-        let mut scenes: HashMap<String, page::Scene> = HashMap::new();
+        let mut scenes: BTreeMap<String, page::Scene> = BTreeMap::new();
         let mut scenes_ids_map: HashMap<String, i32> = HashMap::new();
-        let mut next_id = 0;
+        let mut page_ids = page::PageIds::load();
         for r in &self.renderers {
             for s in &r.scenes {
+                let id = page_ids.get(&page::scene_key(&s.name));
                 scenes.insert(
-                    next_id.to_string(),
+                    id.to_string(),
                     page::Scene {
-                        id: next_id,
+                        id,
                         name: s.name.clone(),
                         renderer: r.renderer.clone(),
                         reference: s.png.clone(),
                         thumbnail: s.thumb.clone(),
+                        retired: s.retired,
+                        tags: s.tags.clone(),
                     },
                 );
-                scenes_ids_map.insert(s.name.clone(), next_id);
-                next_id += 1;
+                scenes_ids_map.insert(s.name.clone(), id);
             }
         }
+        page_ids.save();
         let scenes_data =
             serde_json::to_string_pretty(&scenes).expect("Error serializing page scenes.");
 
         // metrics
-        let mut metrics: HashMap<String, page::Metric> = HashMap::new();
+        let mut metrics: BTreeMap<String, page::Metric> = BTreeMap::new();
         for m in &self.metrics {
             metrics.insert(
                 m.acronym.clone(),
@@ -372,51 +675,92 @@ impl Workspace {
                     reference: m.reference.clone(),
                     lower_is_better: m.lower_is_better,
                     has_error_map: m.has_error_map,
+                    error_map_scale: m.error_map_scale,
                 },
             );
         }
         let metrics_data =
             serde_json::to_string_pretty(&metrics).expect("Error serializing page metrics.");
 
+        // error maps
+        for (t, r) in self
+            .denoisers
+            .iter()
+            .chain(self.samplers.iter())
+            .flat_map(|t| t.results.iter().map(move |r| (t, r)))
+        {
+            for m in self.metrics.iter().filter(|m| m.has_error_map) {
+                if r.metrics.contains_key(&m.acronym) && !r.error_maps.contains_key(&m.acronym) {
+                    log::warn!(
+                        "missing {} error map: technique {}, scene {}, spp {}",
+                        m.acronym,
+                        t.short_name,
+                        r.scene_name,
+                        r.spp_budget
+                    );
+                }
+            }
+        }
+
         // results
-        let mut results: HashMap<String, page::Result> = HashMap::new();
-        next_id = 0;
+        let mut results: BTreeMap<String, page::Result> = BTreeMap::new();
+        let scene_id = |tech: &Technique, r: &Result| -> Option<i32> {
+            let id = scenes_ids_map.get(&r.scene_name).cloned();
+            if id.is_none() {
+                log::warn!(
+                    "result skipped (scene not found): technique {}, scene {}, spp {}",
+                    tech.short_name,
+                    r.scene_name,
+                    r.spp_budget
+                );
+            }
+            id
+        };
         for f in &self.denoisers {
             for r in &f.results {
+                let scene_id = match scene_id(f, r) {
+                    Some(id) => id,
+                    None => continue,
+                };
                 results.insert(
-                    next_id.to_string(),
+                    r.id.to_string(),
                     page::Result {
-                        scene_id: *scenes_ids_map.get(&r.scene_name).unwrap(),
+                        scene_id,
                         spp: r.spp_budget,
                         filter_version_id: f.id,
                         exec_time: r.exec_time.time_ms,
                         aborted: r.aborted,
                         metrics: r.metrics.clone(),
+                        error_maps: r.error_maps.clone(),
+                        logs: if f.private { r.logs.clone() } else { HashMap::new() },
                     },
                 );
-                next_id += 1;
             }
         }
         let results_data =
             serde_json::to_string_pretty(&results).expect("Error serializing page results.");
 
         // samplers results
-        let mut samplers_results: HashMap<String, page::Result> = HashMap::new();
-        next_id = 0;
+        let mut samplers_results: BTreeMap<String, page::Result> = BTreeMap::new();
         for f in &self.samplers {
             for r in &f.results {
+                let scene_id = match scene_id(f, r) {
+                    Some(id) => id,
+                    None => continue,
+                };
                 samplers_results.insert(
-                    next_id.to_string(),
+                    r.id.to_string(),
                     page::Result {
-                        scene_id: *scenes_ids_map.get(&r.scene_name).unwrap(),
+                        scene_id,
                         spp: r.spp_budget,
                         filter_version_id: f.id,
                         exec_time: r.exec_time.time_ms,
                         aborted: r.aborted,
                         metrics: r.metrics.clone(),
+                        error_maps: r.error_maps.clone(),
+                        logs: if f.private { r.logs.clone() } else { HashMap::new() },
                     },
                 );
-                next_id += 1;
             }
         }
         let samplers_results_data = serde_json::to_string_pretty(&samplers_results)
@@ -424,8 +768,15 @@ impl Workspace {
 
         // filters
         let mut filters: Vec<page::Technique> = Vec::new();
+        let valid_results = |tech: &Technique| -> Vec<i32> {
+            tech.results
+                .iter()
+                .filter(|r| scenes_ids_map.contains_key(&r.scene_name))
+                .map(|r| r.id)
+                .collect()
+        };
         for f in &self.denoisers {
-            let results = f.results.iter().map(|r| r.id).collect();
+            let results = valid_results(f);
             filters.push(page::Technique {
                 id: f.id,
                 name: f.short_name.clone(),
@@ -447,7 +798,7 @@ impl Workspace {
         // samplers
         let mut samplers: Vec<page::Technique> = Vec::new();
         for f in &self.samplers {
-            let results = f.results.iter().map(|r| r.id).collect();
+            let results = valid_results(f);
             samplers.push(page::Technique {
                 id: f.id,
                 name: f.short_name.clone(),
@@ -466,6 +817,121 @@ impl Workspace {
         let samplers_data =
             serde_json::to_string_pretty(&samplers).expect("Error serializing page filters.");
 
+        // aggregates (all active scenes and per tag)
+        let mut tag_scenes: BTreeMap<&str, HashSet<String>> = BTreeMap::new();
+        let mut active_scenes: HashSet<String> = HashSet::new();
+        for s in self.renderers.iter().flat_map(|r| &r.scenes).filter(|s| !s.retired) {
+            active_scenes.insert(s.name.clone());
+            for tag in &s.tags {
+                tag_scenes
+                    .entry(tag)
+                    .or_insert_with(HashSet::new)
+                    .insert(s.name.clone());
+            }
+        }
+        let suites = suites::load_all();
+        let suite_scenes: Vec<(String, HashSet<String>)> =
+            suites.iter().map(|s| (s.id(), s.scene_set())).collect();
+        // only the results all techniques of the group have are aggregated, so the aggregates are comparable
+        let export_aggregates = |techs: &Vec<Technique>| -> String {
+            let common = common_results(techs);
+            let scene_results = |scenes: &HashSet<String>| -> HashSet<(String, i32)> {
+                common.iter().filter(|(scene, _)| scenes.contains(scene)).cloned().collect()
+            };
+            let all_results = scene_results(&active_scenes);
+            let tags_results: BTreeMap<&str, HashSet<(String, i32)>> =
+                tag_scenes.iter().map(|(tag, scenes)| (*tag, scene_results(scenes))).collect();
+            let suites_results: Vec<(&String, HashSet<(String, i32)>)> =
+                suite_scenes.iter().map(|(id, scenes)| (id, scene_results(scenes))).collect();
+            let num_scenes = page::AggregateScenes {
+                all: count_scenes(&all_results),
+                tags: tags_results
+                    .iter()
+                    .map(|(tag, results)| (String::from(*tag), count_scenes(results)))
+                    .collect(),
+                suites: suites_results
+                    .iter()
+                    .map(|(id, results)| ((*id).clone(), count_scenes(results)))
+                    .collect(),
+            };
+            let aggregates: Vec<page::Aggregate> = techs
+                .iter()
+                .map(|t| page::Aggregate {
+                    technique_id: t.id,
+                    all: t.summary_over(&all_results),
+                    tags: tags_results
+                        .iter()
+                        .map(|(tag, results)| (String::from(*tag), t.summary_over(results)))
+                        .collect(),
+                    suites: suites_results
+                        .iter()
+                        .map(|(id, results)| ((*id).clone(), t.summary_over(results)))
+                        .collect(),
+                    num_scenes: num_scenes.clone(),
+                })
+                .collect();
+            serde_json::to_string_pretty(&aggregates).expect("Error serializing page aggregates.")
+        };
+        let aggregates_data = export_aggregates(&self.denoisers);
+        let samplers_aggregates_data = export_aggregates(&self.samplers);
+
+        // suites
+        let page_suites: Vec<page::Suite> = suites
+            .iter()
+            .zip(&suite_scenes)
+            .map(|(suite, (id, scenes))| {
+                for scene in scenes.iter().filter(|s| !scenes_ids_map.contains_key(*s)) {
+                    log::warn!("suite {}: scene {} not found", id, scene);
+                }
+                page::Suite {
+                    id: id.clone(),
+                    name: suite.name.clone(),
+                    version: suite.version,
+                    description: suite.description.clone(),
+                    scenes_ids: suite
+                        .scenes
+                        .iter()
+                        .filter_map(|s| scenes_ids_map.get(s).cloned())
+                        .collect(),
+                    complete_techniques: self
+                        .denoisers
+                        .iter()
+                        .chain(self.samplers.iter())
+                        .filter(|t| scenes.is_subset(&t.scenes()))
+                        .map(|t| t.id)
+                        .collect(),
+                }
+            })
+            .collect();
+        let suites_data =
+            serde_json::to_string_pretty(&page_suites).expect("Error serializing page suites.");
+
+        // publication history
+        let registry = reg::Registry::load();
+        let export_history = |group: &TechniqueType| -> String {
+            let history: Vec<page::History> = registry
+                .get_history(group)
+                .map(|(id, name, records)| page::History {
+                    technique_id: page::technique_id(id),
+                    name: name.clone(),
+                    publications: records
+                        .iter()
+                        .map(|r| page::Publication {
+                            uuid: r.uuid.clone(),
+                            commit: r.commit_sha.clone(),
+                            date: r.date.to_rfc3339(),
+                            event: format!("{:?}", r.event),
+                            num_scenes: r.num_scenes,
+                            summary: r.summary.clone(),
+                        })
+                        .collect(),
+                })
+                .collect();
+            serde_json::to_string_pretty(&history).expect("Error serializing page history.")
+        };
+        let history_data = export_history(&TechniqueType::DENOISER);
+        let samplers_history_data = export_history(&TechniqueType::SAMPLER);
+
         fs::write(path.join("scenes.json"), &scenes_data).expect("Error saving page scenes.");
         fs::write(path.join("iqa_metrics.json"), &metrics_data)
             .expect("Error saving page metrics.");
@@ -474,11 +940,139 @@ impl Workspace {
             .expect("Error saving page results.");
         fs::write(path.join("filters.json"), &filters_data).expect("Error saving page filters.");
         fs::write(path.join("samplers.json"), &samplers_data).expect("Error saving page samplers.");
+        fs::write(path.join("suites.json"), &suites_data).expect("Error saving page suites.");
+        fs::write(path.join("history.json"), &history_data).expect("Error saving page history.");
+        fs::write(path.join("aggregates.json"), &aggregates_data)
+            .expect("Error saving page aggregates.");
+        fs::write(path.join("samplers_aggregates.json"), &samplers_aggregates_data)
+            .expect("Error saving page aggregates.");
+        fs::write(path.join("samplers_history.json"), &samplers_history_data)
+            .expect("Error saving page history.");
+    }
+}
+
+/// Generates a report comparing the results of a technique's workspace against the published techniques.
+pub fn generate_report(
+    group: &TechniqueType,
+    id: &str,
+    uuid: &str,
+    format: report::Format,
+) -> WPResult<String> {
+    let tech = Technique::read(
+        page::technique_id(id),
+        paths::tech_workspace_path(group, id, uuid),
+    )?;
+    let name = tech.short_name.clone();
+    let mut wp = Workspace::load();
+    wp.insert_technique(group, tech);
+    let techs = match group {
+        TechniqueType::DENOISER => &wp.denoisers,
+        TechniqueType::SAMPLER => &wp.samplers,
+    };
+    let subject = techs.iter().find(|t| t.short_name == name).unwrap();
+
+    let public_page = paths::public_page_path();
+    // the public page only has the thumbnails of the published workspace
+    let is_published = reg::Registry::load()
+        .get_published(group)
+        .any(|(p_id, p_uuid)| p_id == id && p_uuid == uuid);
+    let data_dir = if is_published {
+        public_page.join("data")
+    } else {
+        public_page.join(uuid).join("data")
+    };
+    let result_thumb = |scene: &str, spp: i32| -> Option<PathBuf> {
+        let thumb = data_dir
+            .join(group.as_str())
+            .join(&name)
+            .join("default")
+            .join(scene)
+            .join(images::thumb_path(Path::new(&format!("{}_0.exr", spp))));
+        Some(thumb).filter(|p| p.is_file())
+    };
+
+    let mut tables = Vec::new();
+    for r in &wp.renderers {
+        for s in &r.scenes {
+            let mut spps: Vec<i32> = subject
+                .results
+                .iter()
+                .filter(|res| res.scene_name == s.name)
+                .map(|res| res.spp_budget)
+                .collect();
+            spps.sort();
+            for spp in spps {
+                let rows = techs
+                    .iter()
+                    .filter_map(|t| {
+                        t.results
+                            .iter()
+                            .find(|res| res.scene_name == s.name && res.spp_budget == spp)
+                            .map(|res| report::Row {
+                                technique: t.short_name.clone(),
+                                aborted: res.aborted,
+                                metrics: res.metrics.clone(),
+                            })
+                    })
+                    .collect();
+                let reference_thumb = public_page.join("scenes").join(&s.thumb);
+                tables.push(report::Table {
+                    scene: s.name.clone(),
+                    renderer: r.renderer.clone(),
+                    spp,
+                    retired: s.retired,
+                    reference_thumb: Some(reference_thumb).filter(|p| p.is_file()),
+                    result_thumb: result_thumb(&s.name, spp),
+                    rows,
+                });
+            }
+        }
+    }
+
+    let report = report::Report {
+        technique: name.clone(),
+        uuid: String::from(uuid),
+        group: String::from(group.as_str()),
+        suite: reg::Registry::load().workspace_suite(id, uuid).cloned(),
+        metrics: wp
+            .metrics
+            .iter()
+            .map(|m| report::Metric {
+                acronym: m.acronym.clone(),
+                name: m.name.clone(),
+                lower_is_better: m.lower_is_better,
+            })
+            .collect(),
+        tables,
+    };
+    Ok(report.render(format))
+}
+
+/// Records the current results of a technique's workspace in its publication history.
+///
+/// The registry is modified but not saved.
+pub fn record_history(
+    registry: &mut reg::Registry,
+    group: &TechniqueType,
+    id: &str,
+    uuid: &str,
+    event: reg::HistoryEvent,
+) -> WPResult<()> {
+    let tech = Technique::read(
+        page::technique_id(id),
+        paths::tech_workspace_path(group, id, uuid),
+    )?;
+    let scenes: HashSet<String> = tech.scenes().intersection(&active_scenes()).cloned().collect();
+    match registry.add_history(id, uuid, event, scenes.len(), tech.summary(&scenes)) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(Error::UuidNotFound),
     }
 }
 
-/// Create a temporary workspace for a technique including missing scenes.
-/// Returns Ok(true) if any missing scene was included.
+/// Create a temporary workspace for a technique including missing results.
+///
+/// Results are missing for each (scene, spp) the technique didn't run yet.
+/// Returns Ok(true) if any missing result was included.
 pub fn create_tmp_technique_workspace(
     group: &TechniqueType,
     proj: ci::ProjectInfo,
@@ -491,7 +1085,7 @@ pub fn create_tmp_technique_workspace(
     fs::create_dir_all(&tmp_workspace).expect("Failed to create temporary workspace");
 
     let tech = match Technique::read(
-        proj.id.parse().unwrap(),
+        page::technique_id(&proj.id),
         paths::tech_workspace_path(group, &proj.id, &uuid),
     ) {
         Ok(tech) => tech,
@@ -500,17 +1094,8 @@ pub fn create_tmp_technique_workspace(
         }
     };
 
-    // find missing scenes and generate config
-    let tech_scenes = tech.scenes();
-    let data = fs::read_to_string(paths::scenes_path().join(".fbksd-scenes-cache.json"))
-        .expect("Failed reading the scenes file");
-    let all_scenes: Vec<Renderer> =
-        serde_json::from_str(&data).expect("Failed deserializing the info json");
-    let all_scenes: HashSet<String> = all_scenes
-        .iter()
-        .flat_map(|r| r.scenes.iter().map(|s| s.name.clone()))
-        .collect();
-    let missing_scenes: HashSet<&String> = all_scenes.difference(&tech_scenes).collect();
+    // find missing results and generate config
+    let missing_scenes = tech.missing(&scenes_spps());
     if missing_scenes.is_empty() {
         return Ok(false);
     }
@@ -541,11 +1126,206 @@ pub fn create_tmp_technique_workspace(
     Ok(true)
 }
 
-/// Creates a new temporary workspace configured with all scenes.
+/// Creates a new temporary workspace equivalent to the one a manifest was produced in.
+///
+/// The technique binaries are copied from its workspace, which must still exist.
+pub fn create_tmp_manifest_workspace(manifest: &Manifest) -> WPResult<()> {
+    let install = paths::tech_install_path(&manifest.group, &manifest.id, &manifest.uuid);
+    if !install.is_dir() {
+        return Err(Error::UuidNotFound);
+    }
+    let tmp_workspace = paths::tmp_workspace_path();
+    if tmp_workspace.is_dir() {
+        fs::remove_dir_all(&tmp_workspace).expect("Failed to clean temporary workspace dir");
+    }
+    fs::create_dir_all(&tmp_workspace).expect("Failed to create temporary workspace");
+    config::gen_config_from(&tmp_workspace, &manifest.config);
+
+    // copy binaries
+    let dest = tmp_workspace.join(manifest.group.as_str()).join(&manifest.id);
+    let status = Command::new("rsync")
+        .args(&["-a", install.join("").to_str().unwrap(), dest.to_str().unwrap()])
+        .status();
+    if status.is_err() || !status.unwrap().success() {
+        return Err(Error::Unspecified);
+    }
+    Ok(())
+}
+
+/// Groups the published techniques (ids) by the docker image their workspaces were built with.
+pub fn published_by_image() -> BTreeMap<String, HashSet<String>> {
+    let reg = reg::Registry::load();
+    let mut images: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    for group in vec![TechniqueType::DENOISER, TechniqueType::SAMPLER] {
+        for (id, uuid) in reg.get_published(&group) {
+            let image = match reg.workspace_docker_image(id, uuid) {
+                Some(image) if !image.is_empty() => image.clone(),
+                _ => String::from(docker::DEFAULT_IMAGE),
+            };
+            images.entry(image).or_insert_with(HashSet::new).insert(id.clone());
+        }
+    }
+    images
+}
+
+/// What a run-all would do for the published techniques of a docker image.
+pub struct RunPlan {
+    pub image: String,
+    /// Scenes that would run, with their spps.
+    pub scenes: BTreeMap<String, Vec<i32>>,
+    pub techniques: Vec<TechniquePlan>,
+}
+
+/// What a run-all would do for a published technique.
+pub struct TechniquePlan {
+    pub id: String,
+    pub name: String,
+    /// Number of results that would run.
+    pub num_new: usize,
+    /// Existing (scene, spp) results, which are kept (not overwritten).
+    pub existing: Vec<(String, i32)>,
+    /// Estimated time of the new results, in milliseconds.
+    pub estimate_ms: i64,
+    /// Number of new results without a time estimate.
+    pub num_unestimated: usize,
+}
+
+impl RunPlan {
+    /// Estimated time of all new results of this plan, in milliseconds.
+    pub fn estimate_ms(&self) -> i64 {
+        self.techniques.iter().map(|t| t.estimate_ms).sum()
+    }
+}
+
+/// Formats a duration in milliseconds as hours and minutes.
+pub fn format_duration(ms: i64) -> String {
+    let minutes = (ms + 59_999) / 60_000;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+impl fmt::Display for RunPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[{}] {} techniques", self.image, self.techniques.len())?;
+        writeln!(f, "  scenes ({}):", self.scenes.len())?;
+        for (scene, spps) in &self.scenes {
+            let spps: Vec<String> = spps.iter().map(|spp| spp.to_string()).collect();
+            writeln!(f, "    {}: {} spp", scene, spps.join(", "))?;
+        }
+        for t in &self.techniques {
+            write!(
+                f,
+                "  {} ({}): {} to run, {} existing (skipped), estimated {}",
+                t.name,
+                t.id,
+                t.num_new,
+                t.existing.len(),
+                format_duration(t.estimate_ms)
+            )?;
+            if t.num_unestimated > 0 {
+                write!(f, " ({} results without estimate)", t.num_unestimated)?;
+            }
+            writeln!(f)?;
+            for (scene, spp) in &t.existing {
+                writeln!(f, "    skipped: {} {} spp", scene, spp)?;
+            }
+        }
+        writeln!(f, "  estimated time: {}", format_duration(self.estimate_ms()))
+    }
+}
+
+/// Plans a run-all (with all scenes or only the ones from a suite), without running anything.
+///
+/// The time of each new result is estimated by the mean execution time of the existing results of the same scene
+/// and spp (from any published technique), or of the same spp if there are none. Aborted results are not used.
+pub fn plan_run_all(suite: Option<&Suite>) -> Vec<RunPlan> {
+    let mut scenes: BTreeMap<String, Vec<i32>> = scenes_spps().into_iter().collect();
+    if let Some(suite) = suite {
+        let suite_scenes = suite.scene_set();
+        scenes = scenes
+            .into_iter()
+            .filter(|(name, _)| suite_scenes.contains(name))
+            .collect();
+    }
+
+    let reg = reg::Registry::load();
+    let mut techs: HashMap<String, Technique> = HashMap::new();
+    for group in vec![TechniqueType::DENOISER, TechniqueType::SAMPLER] {
+        for (id, uuid) in reg.get_published(&group) {
+            let tech = Technique::read(page::technique_id(id), paths::tech_workspace_path(&group, id, uuid))
+                .expect("Failed to read technique");
+            techs.insert(id.clone(), tech);
+        }
+    }
+
+    // mean execution times
+    let mut scene_times: HashMap<(&str, i32), (i64, i64)> = HashMap::new();
+    let mut spp_times: HashMap<i32, (i64, i64)> = HashMap::new();
+    for r in techs.values().flat_map(|t| &t.results).filter(|r| !r.aborted) {
+        for time in vec![
+            scene_times.entry((&r.scene_name, r.spp_budget)).or_insert((0, 0)),
+            spp_times.entry(r.spp_budget).or_insert((0, 0)),
+        ] {
+            time.0 += r.exec_time.time_ms;
+            time.1 += 1;
+        }
+    }
+    let estimate = |scene: &str, spp: i32| -> Option<i64> {
+        scene_times
+            .get(&(scene, spp))
+            .or_else(|| spp_times.get(&spp))
+            .map(|(sum, count)| sum / count)
+    };
+
+    let mut plans = Vec::new();
+    for (image, ids) in published_by_image() {
+        let mut ids: Vec<&String> = ids.iter().collect();
+        ids.sort();
+        let mut techniques = Vec::new();
+        for id in ids {
+            let tech = &techs[id];
+            let existing_results: HashSet<(&str, i32)> = tech
+                .results
+                .iter()
+                .map(|r| (r.scene_name.as_str(), r.spp_budget))
+                .collect();
+            let mut plan = TechniquePlan {
+                id: id.clone(),
+                name: tech.short_name.clone(),
+                num_new: 0,
+                existing: Vec::new(),
+                estimate_ms: 0,
+                num_unestimated: 0,
+            };
+            for (scene, spps) in &scenes {
+                for spp in spps {
+                    if existing_results.contains(&(scene.as_str(), *spp)) {
+                        plan.existing.push((scene.clone(), *spp));
+                        continue;
+                    }
+                    plan.num_new += 1;
+                    match estimate(scene, *spp) {
+                        Some(ms) => plan.estimate_ms += ms,
+                        None => plan.num_unestimated += 1,
+                    }
+                }
+            }
+            techniques.push(plan);
+        }
+        plans.push(RunPlan {
+            image,
+            scenes: scenes.clone(),
+            techniques,
+        });
+    }
+    plans
+}
+
+/// Creates a new temporary workspace configured with all scenes, or only the ones from a suite.
 ///
-/// Optionally you can include all published techniques (results and binaries).
+/// Optionally you can include published techniques (results and binaries): the ones in `published`, or none if
+/// it's None.
 /// This function needs access to the "workspaces" folder and expects `fbksd` in the PATH.
-pub fn create_tmp_workspace(include_published: bool) {
+pub fn create_tmp_workspace(published: Option<&HashSet<String>>, suite: Option<&Suite>) {
     // create temporary workspace
     let tmp_workspace = paths::tmp_workspace_path();
     if tmp_workspace.is_dir() {
@@ -570,11 +1350,11 @@ pub fn create_tmp_workspace(include_published: bool) {
     }
 
     // include published techniques
-    if include_published {
+    if let Some(ids) = published {
         let reg = reg::Registry::load();
         for group in vec![reg::TechniqueType::DENOISER, reg::TechniqueType::SAMPLER] {
             fs::create_dir_all(PathBuf::from("results/.current").join(group.as_str())).unwrap();
-            let published = reg.get_published(&group);
+            let published = reg.get_published(&group).filter(|p| ids.contains(p.0));
             for p in published {
                 let base = paths::tech_workspace_path(&group, &p.0, &p.1);
                 // binaries
@@ -606,55 +1386,250 @@ pub fn create_tmp_workspace(include_published: bool) {
     }
 
     // config
-    if config::fbksd_config().is_err() {
+    if config::fbksd_config(suite).is_err() {
         std::process::exit(1);
     }
 }
 
-/// Save technique data from a temporary workspace to the permanent location.
+/// Creates a new temporary workspace with published techniques (binaries only) configured with the given scenes
+/// (scene name -> spps).
 ///
-/// Data can be copied or moved, and can include the executable or only the results.
-pub fn save_technique_tmp_workspace(id: &str, uuid: &str, include_install: bool, mv: bool) {
+/// Only the techniques in `ids` are included, or all published ones if it's None.
+/// This function needs access to the "workspaces" folder.
+pub fn create_tmp_published_workspace(
+    ids: Option<&HashSet<String>>,
+    scenes: &BTreeMap<String, Vec<i32>>,
+) -> WPResult<()> {
     let tmp_workspace = paths::tmp_workspace_path();
-    let group = reg::Registry::load().technique_type(id).unwrap();
-    let tech = reg::Technique::read(
-        tmp_workspace
-            .join(group.as_str())
-            .join(&id)
-            .join("info.json"),
-    )
-    .unwrap();
-    let src = tmp_workspace
-        .join("results/.current")
-        .join(group.as_str())
-        .join(&tech.short_name)
-        .join("");
-    let dest = paths::tech_results_path(&group, &id, &uuid).join("");
-    if mv {
-        let status = Command::new("mv").args(&[&src, &dest]).status();
-        if status.is_err() || !status.unwrap().success() {
-            std::process::exit(1);
+    if tmp_workspace.is_dir() {
+        fs::remove_dir_all(&tmp_workspace).expect("Failed to clean temporary workspace dir");
+    }
+    fs::create_dir_all(&tmp_workspace).expect("Failed to create temporary workspace");
+
+    let reg = reg::Registry::load();
+    let mut installs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut denoisers: Vec<String> = Vec::new();
+    let mut samplers: Vec<String> = Vec::new();
+    for group in vec![reg::TechniqueType::DENOISER, reg::TechniqueType::SAMPLER] {
+        for p in reg.get_published(&group) {
+            if ids.map_or(false, |ids| !ids.contains(p.0)) {
+                continue;
+            }
+            let install = paths::tech_install_path(&group, &p.0, &p.1);
+            let tech = match reg::Technique::read(install.join("info.json")) {
+                Ok(tech) => tech,
+                Err(_) => return Err(Error::Unspecified),
+            };
+            match group {
+                TechniqueType::DENOISER => denoisers.push(tech.short_name),
+                TechniqueType::SAMPLER => samplers.push(tech.short_name),
+            }
+            installs.push((install.join(""), tmp_workspace.join(group.as_str()).join(&p.0)));
         }
-    } else {
+    }
+    config::gen_config(&tmp_workspace, &denoisers, &samplers, scenes);
+
+    // copy binaries
+    for (src, dest) in installs {
         let status = Command::new("rsync")
-            .args(&[
-                "-a",
-                "--ignore-existing",
-                src.to_str().unwrap(),
-                dest.to_str().unwrap(),
-            ])
+            .args(&["-a", src.to_str().unwrap(), dest.to_str().unwrap()])
             .status();
         if status.is_err() || !status.unwrap().success() {
-            std::process::exit(1);
+            return Err(Error::Unspecified);
         }
     }
+    Ok(())
+}
 
-    if include_install {
-        let src = tmp_workspace.join(group.as_str()).join(&id).join("");
-        let dest = paths::tech_install_path(&group, &id, &uuid).join("");
-        if mv {
-            let status = Command::new("mv").args(&[&src, &dest]).status();
-            if status.is_err() || !status.unwrap().success() {
+/// Progress of a run-all, used to resume it if it's interrupted.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// Suite the run uses.
+    pub suite: Option<String>,
+    /// Images whose techniques finished running and had their results saved.
+    pub completed: Vec<String>,
+    /// Image whose techniques are running in the temporary workspace.
+    pub current: Option<String>,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint of the interrupted run-all, if any.
+    pub fn load() -> Option<Checkpoint> {
+        let data = fs::read_to_string(paths::run_checkpoint_path()).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn save(&self) {
+        let data = serde_json::to_string_pretty(self).expect("Failed to serialize checkpoint");
+        fs::write(paths::run_checkpoint_path(), &data).expect("Failed to save checkpoint");
+    }
+
+    /// Removes the checkpoint file (after the run-all finished).
+    pub fn remove() {
+        let path = paths::run_checkpoint_path();
+        if path.is_file() {
+            fs::remove_file(&path).expect("Failed to remove checkpoint");
+        }
+    }
+}
+
+/// Sets the config used by the next runs in the temporary workspace (see `config::set_current()`).
+pub fn set_tmp_config(name: &str) {
+    config::set_current(paths::tmp_workspace_path(), name);
+}
+
+/// Writes configs for the results of the temporary workspace that have no logs (see `config::write_resume_configs()`).
+///
+/// Results left in shard workspaces are merged first.
+pub fn write_resume_configs() -> Vec<String> {
+    merge_tmp_shards(&existing_tmp_shards());
+    config::write_resume_configs(paths::tmp_workspace_path())
+}
+
+/// Splits the temporary workspace into shard workspaces, to be run in parallel.
+///
+/// Each shard is a copy of the temporary workspace (files are hard-linked) configured with part of its work (see
+/// `config::write_config_shards()`).
+/// Returns the shards paths: at most `workers`, and none if the work can't be split.
+pub fn create_tmp_shards(workers: usize, by: ShardBy) -> Vec<PathBuf> {
+    remove_tmp_shards();
+    let tmp_workspace = paths::tmp_workspace_path();
+    let num_shards = workers.min(config::shard_units(tmp_workspace, by));
+    if num_shards < 2 {
+        return Vec::new();
+    }
+    let shards: Vec<PathBuf> = (0..num_shards).map(paths::tmp_shard_workspace_path).collect();
+    if !create_shards(tmp_workspace, &shards, by) {
+        std::process::exit(1);
+    }
+    shards
+}
+
+/// Copies a workspace into each of the shard paths (hard-linking its files) and writes the shard configs.
+pub(crate) fn create_shards(workspace: &Path, shards: &[PathBuf], by: ShardBy) -> bool {
+    let link_dest = format!("--link-dest={}", workspace.to_str().unwrap());
+    for shard in shards {
+        let status = Command::new("rsync")
+            .args(&[
+                "-a",
+                &link_dest,
+                workspace.join("").to_str().unwrap(),
+                shard.join("").to_str().unwrap(),
+            ])
+            .status();
+        if status.is_err() || !status.unwrap().success() {
+            return false;
+        }
+    }
+    config::write_config_shards(workspace, shards, by);
+    true
+}
+
+/// Merges the results of the shard workspaces into the temporary workspace, and removes the shards.
+pub fn merge_tmp_shards(shards: &[PathBuf]) {
+    let dest = paths::tmp_workspace_path().join("results/.current/");
+    for shard in shards {
+        let src = shard.join("results/.current/");
+        let status = Command::new("rsync")
+            .args(&[
+                "-a",
+                "--ignore-existing",
+                src.to_str().unwrap(),
+                dest.to_str().unwrap(),
+            ])
+            .status();
+        if status.is_err() || !status.unwrap().success() {
+            std::process::exit(1);
+        }
+    }
+    remove_tmp_shards();
+}
+
+/// Returns the existing shard workspaces (e.g. left by an interrupted run).
+pub fn existing_tmp_shards() -> Vec<PathBuf> {
+    let pattern = paths::tmp_shard_workspace_path(0).with_file_name("workspace-shard-*");
+    glob(pattern.to_str().unwrap())
+        .expect("Failed to read glob pattern")
+        .filter_map(|e| e.ok())
+        .collect()
+}
+
+/// Removes all shard workspaces.
+fn remove_tmp_shards() {
+    for shard in existing_tmp_shards() {
+        fs::remove_dir_all(&shard).expect("Failed to remove shard workspace");
+    }
+}
+
+/// Writes aborted results for all results expected from a workspace's config that have no log file.
+///
+/// Used after a run is killed, so the missing results are saved as aborted.
+/// Returns the number of aborted results written.
+pub fn abort_missing_results(workspace: &Path) -> usize {
+    let mut count = 0;
+    for (log, spp) in config::expected_logs(workspace) {
+        let log = workspace.join(log);
+        if log.exists() {
+            continue;
+        }
+        let data = serde_json::json!({
+            "aborted": true,
+            "date": chrono::Utc::now().to_rfc3339(),
+            "exec_time": { "time_ms": 0 },
+            "spp_budget": spp,
+        });
+        fs::create_dir_all(log.parent().unwrap()).expect("Failed to create results folder");
+        fs::write(&log, serde_json::to_string_pretty(&data).unwrap()).expect("Failed to save aborted result");
+        count += 1;
+    }
+    count
+}
+
+/// Save technique data from a temporary workspace to the permanent location.
+///
+/// Data can be copied or moved, and can include the executable or only the results.
+pub fn save_technique_tmp_workspace(id: &str, uuid: &str, include_install: bool, mv: bool) {
+    let tmp_workspace = paths::tmp_workspace_path();
+    let group = reg::Registry::load().technique_type(id).unwrap();
+    let tech = reg::Technique::read(
+        tmp_workspace
+            .join(group.as_str())
+            .join(&id)
+            .join("info.json"),
+    )
+    .unwrap();
+    let src = tmp_workspace
+        .join("results/.current")
+        .join(group.as_str())
+        .join(&tech.short_name)
+        .join("");
+    let dest = paths::tech_results_path(&group, &id, &uuid).join("");
+    let scenes = result_scenes(&src);
+    if mv {
+        let status = Command::new("mv").args(&[&src, &dest]).status();
+        if status.is_err() || !status.unwrap().success() {
+            std::process::exit(1);
+        }
+    } else {
+        let status = Command::new("rsync")
+            .args(&[
+                "-a",
+                "--ignore-existing",
+                src.to_str().unwrap(),
+                dest.to_str().unwrap(),
+            ])
+            .status();
+        if status.is_err() || !status.unwrap().success() {
+            std::process::exit(1);
+        }
+    }
+
+    if include_install {
+        let src = tmp_workspace.join(group.as_str()).join(&id).join("");
+        let dest = paths::tech_install_path(&group, &id, &uuid).join("");
+        if mv {
+            let status = Command::new("mv").args(&[&src, &dest]).status();
+            if status.is_err() || !status.unwrap().success() {
                 std::process::exit(1);
             }
         } else {
@@ -671,9 +1646,11 @@ pub fn save_technique_tmp_workspace(id: &str, uuid: &str, include_install: bool,
             }
         }
     }
+    record_scene_hashes(&group, id, uuid, &scenes, false);
+    manifest::record(Manifest::new(&group, id, uuid, &tech.short_name, tmp_workspace));
 }
 
-/// Saves data from the temporary workspace to the permanent location for all published techniques.
+/// Saves data from the temporary workspace to the permanent location for all published techniques in it.
 ///
 /// Args:
 ///  - include_install: also saves the techniques install ("<group>/*") folder
@@ -687,7 +1664,7 @@ pub fn save_tmp_workspace(include_install: bool, mv: bool) {
     let reg = reg::Registry::load();
     for group in vec![reg::TechniqueType::DENOISER, reg::TechniqueType::SAMPLER] {
         let published = reg.get_published(&group);
-        for p in published {
+        for p in published.filter(|p| tmp_workspace.join(group.as_str()).join(p.0).is_dir()) {
             save_technique_tmp_workspace(&p.0, &p.1, include_install, mv);
         }
     }
@@ -695,33 +1672,53 @@ pub fn save_tmp_workspace(include_install: bool, mv: bool) {
 
 /// Export result images from a specific technique slot.
 ///
+/// The EXR outputs are converted to web images (png and thumbnail) in the page, and the error maps to false-color
+/// images.
+///
 /// Args:
 ///  - src: `workspaces/<group>/<id>/<uuid>/results` directory
 ///  - dest: `<page>/data/<group>/<tech name>` directory
-///  - ignore_existing: avoid transferring files that already exist in the destination
+///  - ignore_existing: avoid regenerating images that already exist in the destination
 pub fn export_technique_images(src: &Path, dest: &Path, ignore_existing: bool) -> bool {
-    let src = src.join("");
-    let dest = dest.join("");
-    let mut args = vec![
-        "-a",
-        "--include",
-        "*.png",
-        "--include",
-        "*/",
-        "--exclude",
-        "*",
-        "--delete",
-    ];
-    if ignore_existing {
-        args.push("--ignore-existing");
-    }
-    args.push(src.to_str().unwrap());
-    args.push(dest.to_str().unwrap());
-    let status = Command::new("rsync").args(&args).status();
-    if status.is_err() || !status.unwrap().success() {
-        return false;
+    let config = SystemConfig::load().images;
+    let scales: HashMap<String, f32> = read_metrics(paths::iqa_path())
+        .into_iter()
+        .filter(|m| m.has_error_map)
+        .map(|m| (m.acronym, m.error_map_scale))
+        .collect();
+    images::export_dir(src, dest, ignore_existing, &config, &scales)
+}
+
+/// Copies the run logs (`*_stdout.log`, `*_stderr.log`) of a technique results folder to a page data folder.
+pub fn export_technique_logs(src: &Path, dest: &Path) -> bool {
+    let mut ok = true;
+    for stream in &["stdout", "stderr"] {
+        let pattern = src.join(format!("**/*_{}.log", stream));
+        for log in glob(pattern.to_str().unwrap())
+            .expect("Failed to read glob pattern")
+            .filter_map(|p| p.ok())
+        {
+            let dest = dest.join(log.strip_prefix(src).unwrap());
+            if fs::create_dir_all(dest.parent().unwrap()).is_err() || fs::copy(&log, &dest).is_err() {
+                log::error!("failed to copy log {:?}", log);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Removes the run logs from a technique page data folder (see `export_technique_logs()`).
+pub fn remove_technique_logs(dir: &Path) {
+    for stream in &["stdout", "stderr"] {
+        let pattern = dir.join(format!("**/*_{}.log", stream));
+        for log in glob(pattern.to_str().unwrap())
+            .expect("Failed to read glob pattern")
+            .filter_map(|p| p.ok())
+        {
+            fs::remove_file(&log).expect("Failed to remove log");
+        }
     }
-    return true;
 }
 
 /// Export result images from all published techniques to the public page.
@@ -746,73 +1743,721 @@ pub fn export_images() {
     }
 }
 
-/// Re-scan the scenes folder and updates the cache file.
-pub fn update_scenes() {
-    let scenes_dir = paths::scenes_path();
+/// Severity of a problem found while scanning the scenes folder.
+#[derive(Debug, PartialEq)]
+pub enum Severity {
+    /// The scenes cache can't be updated.
+    Error,
+    /// The scenes cache can be updated, but something may need attention.
+    Warning,
+}
+
+/// Problem found while scanning the scenes folder.
+#[derive(Debug)]
+pub struct ScanProblem {
+    pub severity: Severity,
+    /// Scene file where the problem was found (with the scene index for `fbksd-scenes.json` files).
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ScanProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.location, self.message)
+    }
+}
+
+/// Scans the scenes folder, validating every scene file.
+///
+/// Returns the renderers with all valid scenes found, and all problems found.
+/// The scenes hashes are only computed with `hash` (they are left empty otherwise).
+pub fn scan_scenes(hash: bool) -> (Vec<Renderer>, Vec<ScanProblem>) {
+    scan_scenes_dir(paths::scenes_path(), hash)
+}
+
+/// Scans the given scenes folder (see `scan_scenes()`).
+fn scan_scenes_dir(scenes_dir: &Path, hash: bool) -> (Vec<Renderer>, Vec<ScanProblem>) {
+    let mut problems: Vec<ScanProblem> = Vec::new();
+    let mut renderers: Vec<Renderer> = Vec::new();
     if !scenes_dir.is_dir() {
-        eprintln!("scenes folder does not exist");
-        std::process::exit(1);
+        problems.push(ScanProblem {
+            severity: Severity::Error,
+            location: String::from(scenes_dir.to_str().unwrap()),
+            message: String::from("scenes folder does not exist"),
+        });
+        return (renderers, problems);
     }
 
-    fn fix_scene_paths(scene: &mut Scene, path: &Path) {
-        scene.path = path
-            .parent()
-            .unwrap()
-            .join(&scene.path)
-            .to_str()
-            .unwrap()
-            .to_string();
-        scene.ref_img = path
-            .parent()
-            .unwrap()
-            .join(&scene.ref_img)
-            .to_str()
-            .unwrap()
-            .to_string();
+    fn error(location: &str, message: String) -> ScanProblem {
+        ScanProblem {
+            severity: Severity::Error,
+            location: String::from(location),
+            message,
+        }
     }
 
-    let mut renderers: Vec<Renderer> = Vec::new();
-    for entry in fs::read_dir(&scenes_dir).unwrap() {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        if path.is_dir() {
-            let _cd = utils::CD::new(&path);
-            let mut scenes: Vec<Scene> = Vec::new();
-            for scene_file in glob("**/fbksd-scene.json").expect("Failed to read glob pattern") {
-                if let Ok(path) = scene_file {
-                    let data =
-                        fs::read_to_string(&path).expect("Failed reading the \"info.json\" file");
-                    let mut scene: Scene =
-                        serde_json::from_str(&data).expect("Failed to deserialize scene entry");
-                    fix_scene_paths(&mut scene, &path);
-                    scenes.push(scene);
+    fn warning(location: &str, message: String) -> ScanProblem {
+        ScanProblem {
+            severity: Severity::Warning,
+            location: String::from(location),
+            message,
+        }
+    }
+
+    // Validates the scene and makes its paths relative to the renderer folder.
+    fn check_scene(scene: &mut Scene, path: &Path, location: &str, problems: &mut Vec<ScanProblem>) {
+        let dir = path.parent().unwrap();
+        scene.path = dir.join(&scene.path).to_str().unwrap().to_string();
+        scene.ref_img = dir.join(&scene.ref_img).to_str().unwrap().to_string();
+        if scene.name.is_empty() {
+            problems.push(error(location, String::from("empty scene name")));
+        }
+        if scene.spps.iter().flatten().chain(&scene.extra_spps).any(|spp| *spp <= 0) {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": spps must be positive", scene.name),
+            ));
+        }
+        if scene.spps.as_ref().map_or(false, |spps| spps.is_empty()) {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": empty spps list", scene.name),
+            ));
+        }
+        if scene.tags.iter().any(|t| t.trim().is_empty()) {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": empty tag", scene.name),
+            ));
+        }
+        if !Path::new(&scene.path).exists() {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": path \"{}\" not found", scene.name, scene.path),
+            ));
+        }
+        let ref_img = Path::new(&scene.ref_img);
+        if !ref_img.is_file() {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": ref-img \"{}\" not found", scene.name, scene.ref_img),
+            ));
+        } else if ref_img.extension().map_or(true, |e| e != "exr") {
+            problems.push(error(
+                location,
+                format!("scene \"{}\": ref-img \"{}\" is not an EXR image", scene.name, scene.ref_img),
+            ));
+        } else {
+            for companion in vec![images::png_path(ref_img), images::thumb_path(ref_img)] {
+                if !companion.is_file() {
+                    problems.push(warning(
+                        location,
+                        format!(
+                            "scene \"{}\": \"{}\" not found (it will be generated from ref-img)",
+                            scene.name,
+                            companion.to_str().unwrap()
+                        ),
+                    ));
                 }
             }
-            for scene_file in glob("**/fbksd-scenes.json").expect("Failed to read glob pattern") {
-                if let Ok(path) = scene_file {
-                    let data =
-                        fs::read_to_string(&path).expect("Failed reading the \"info.json\" file");
-                    let mut scenes_vec: Vec<Scene> =
-                        serde_json::from_str(&data).expect("Failed to deserialize scene entry");
-                    for mut scene in &mut scenes_vec {
-                        fix_scene_paths(&mut scene, &path);
+        }
+        scene.hashes = SceneHashes::default();
+    }
+
+    // Computes the hashes of the scenes of a renderer folder (see `SceneHashes`).
+    //
+    // Scenes may share a folder, so the folders hashes skip the definition files, reference images and scene files
+    // of all scenes, and each folder is hashed only once. A scene file is hashed together with its folder.
+    fn hash_scenes(scenes: &mut [(String, Scene)], definitions: &[PathBuf], problems: &mut Vec<ScanProblem>) {
+        let mut exclude: Vec<PathBuf> = definitions.to_vec();
+        for (_, scene) in scenes.iter() {
+            let ref_img = Path::new(&scene.ref_img);
+            exclude.extend(vec![ref_img.to_path_buf(), images::png_path(ref_img), images::thumb_path(ref_img)]);
+            if Path::new(&scene.path).is_file() {
+                exclude.push(PathBuf::from(&scene.path));
+            }
+        }
+        let mut dir_hashes: HashMap<PathBuf, std::result::Result<String, String>> = HashMap::new();
+        for (location, scene) in scenes.iter_mut() {
+            let scene_path = Path::new(&scene.path);
+            let ref_img = Path::new(&scene.ref_img);
+            if !scene_path.exists() || !ref_img.is_file() {
+                continue;
+            }
+            let dir = if scene_path.is_dir() {
+                scene_path
+            } else {
+                scene_path.parent().unwrap()
+            };
+            let dir_hash = dir_hashes
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| utils::sha256_dir(dir, &exclude).map_err(|err| err.to_string()))
+                .clone();
+            let scene_hash = dir_hash.and_then(|dir_hash| {
+                if scene_path.is_dir() {
+                    return Ok(dir_hash);
+                }
+                utils::sha256(scene_path)
+                    .and_then(|file_hash| utils::sha256_str(&format!("{}\n{}\n", dir_hash, file_hash)))
+                    .map_err(|err| err.to_string())
+            });
+            match (scene_hash, utils::sha256(ref_img).map_err(|err| err.to_string())) {
+                (Ok(scene_hash), Ok(ref_hash)) => {
+                    scene.hashes.scene = scene_hash;
+                    scene.hashes.reference = ref_hash;
+                }
+                (Err(err), _) | (_, Err(err)) => problems.push(error(
+                    location,
+                    format!("scene \"{}\": failed to compute hashes: {}", scene.name, err),
+                )),
+            }
+        }
+    }
+
+    // scene name -> location of its first definition
+    let mut locations: HashMap<String, String> = HashMap::new();
+    let mut entries: Vec<PathBuf> = match fs::read_dir(scenes_dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect(),
+        Err(err) => {
+            problems.push(error(scenes_dir.to_str().unwrap(), err.to_string()));
+            return (renderers, problems);
+        }
+    };
+    entries.sort();
+    for path in entries {
+        let renderer_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let _cd = utils::CD::new(&path);
+        let mut scenes: Vec<(String, Scene)> = Vec::new();
+        let mut definitions: Vec<PathBuf> = Vec::new();
+        for scene_file in glob("**/fbksd-scene.json").expect("Failed to read glob pattern") {
+            if let Ok(path) = scene_file {
+                definitions.push(path.clone());
+                let location = format!("{}/{}", renderer_name, path.to_str().unwrap());
+                let data = match fs::read_to_string(&path) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        problems.push(error(&location, err.to_string()));
+                        continue;
                     }
-                    scenes.extend(scenes_vec);
+                };
+                match serde_json::from_str::<Scene>(&data) {
+                    Ok(mut scene) => {
+                        check_scene(&mut scene, &path, &location, &mut problems);
+                        scenes.push((location, scene));
+                    }
+                    Err(err) => problems.push(error(&location, err.to_string())),
                 }
             }
+        }
+        for scene_file in glob("**/fbksd-scenes.json").expect("Failed to read glob pattern") {
+            if let Ok(path) = scene_file {
+                definitions.push(path.clone());
+                let location = format!("{}/{}", renderer_name, path.to_str().unwrap());
+                let data = match fs::read_to_string(&path) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        problems.push(error(&location, err.to_string()));
+                        continue;
+                    }
+                };
+                match serde_json::from_str::<Vec<serde_json::Value>>(&data) {
+                    Ok(values) => {
+                        for (i, value) in values.into_iter().enumerate() {
+                            let location = format!("{}[{}]", location, i);
+                            match serde_json::from_value::<Scene>(value) {
+                                Ok(mut scene) => {
+                                    check_scene(&mut scene, &path, &location, &mut problems);
+                                    scenes.push((location, scene));
+                                }
+                                Err(err) => problems.push(error(&location, err.to_string())),
+                            }
+                        }
+                    }
+                    Err(err) => problems.push(error(&location, err.to_string())),
+                }
+            }
+        }
 
-            let renderer_name = path.file_name().unwrap().to_str().unwrap();
-            renderers.push(Renderer {
-                renderer: renderer_name.to_string(),
-                scenes,
-            });
+        if hash {
+            hash_scenes(&mut scenes, &definitions, &mut problems);
         }
+
+        let mut renderer_scenes = Vec::new();
+        for (location, scene) in scenes {
+            if let Some(first) = locations.get(&scene.name) {
+                problems.push(error(
+                    &location,
+                    format!("scene name \"{}\" already used in {}", scene.name, first),
+                ));
+                continue;
+            }
+            locations.insert(scene.name.clone(), location);
+            renderer_scenes.push(scene);
+        }
+        renderers.push(Renderer {
+            renderer: renderer_name,
+            scenes: renderer_scenes,
+        });
+    }
+    (renderers, problems)
+}
+
+/// Re-scan the scenes folder and updates the cache file.
+///
+/// All problems found are printed. If any of them is an error, the cache file is not updated.
+/// With `dry_run`, the folder is only validated (without hashing the scenes) and the cache file is never updated.
+/// The differences from the previous cache are printed, and the added scenes are included in the pending scenes
+/// (see `read_pending_scenes()`).
+pub fn update_scenes(dry_run: bool) -> WPResult<ScenesDiff> {
+    let (renderers, problems) = scan_scenes(!dry_run);
+    for p in &problems {
+        eprintln!("{}", p);
+    }
+    let num_errors = problems
+        .iter()
+        .filter(|p| p.severity == Severity::Error)
+        .count();
+    let num_scenes: usize = renderers.iter().map(|r| r.scenes.len()).sum();
+    println!(
+        "{} scenes found, {} errors, {} warnings",
+        num_scenes,
+        num_errors,
+        problems.len() - num_errors
+    );
+    if num_errors > 0 {
+        eprintln!("scenes cache not updated");
+        return Err(Error::InvalidScenes);
+    }
+
+    let old = if paths::scenes_cache_path().is_file() {
+        read_scenes_cache()
+    } else {
+        Vec::new()
+    };
+    let diff = ScenesDiff::new(&old, &renderers);
+    if diff.is_empty() {
+        println!("no changes in the scenes");
+    } else {
+        print!("{}", diff);
+    }
+    if dry_run {
+        return Ok(diff);
     }
 
     let data =
         serde_json::to_string_pretty(&renderers).expect("Failed to serialize scenes cache file");
-    fs::write(scenes_dir.join(".fbksd-scenes-cache.json"), &data)
-        .expect("Failed to save scenes cache file");
+    fs::write(paths::scenes_cache_path(), &data).expect("Failed to save scenes cache file");
+
+    let active: HashSet<String> = renderers
+        .iter()
+        .flat_map(|r| r.scenes.iter().filter(|s| !s.retired).map(|s| s.name.clone()))
+        .collect();
+    let mut pending = read_pending_scenes();
+    pending.retain(|s| active.contains(s));
+    pending.extend(diff.added.iter().filter(|s| active.contains(*s)).cloned());
+    write_pending_scenes(&pending);
+    Ok(diff)
+}
+
+/// Removes the given scenes from the pending scenes.
+pub fn remove_pending_scenes(scenes: &[String]) {
+    let mut pending = read_pending_scenes();
+    pending.retain(|s| !scenes.contains(s));
+    write_pending_scenes(&pending);
+}
+
+/// Returns the names of the scenes with results in a results folder (`<version>/<scene>/<spp>_0_log.json`).
+pub fn result_scenes(results: &Path) -> HashSet<String> {
+    glob(results.join("*/*/*_log.json").to_str().unwrap())
+        .expect("Failed to read glob pattern")
+        .filter_map(|e| e.ok())
+        .map(|p| {
+            let dir = p.parent().unwrap();
+            String::from(dir.file_name().unwrap().to_str().unwrap())
+        })
+        .collect()
+}
+
+/// Reads the hashes of the scenes a workspace's results were computed with (scene name -> hashes).
+pub fn read_scene_hashes(group: &TechniqueType, id: &str, uuid: &str) -> HashMap<String, SceneHashes> {
+    let path = paths::tech_scene_hashes_path(group, id, uuid);
+    if !path.is_file() {
+        return HashMap::new();
+    }
+    let data = fs::read_to_string(&path).expect("Failed to read scene hashes file");
+    serde_json::from_str(&data).expect("Failed to deserialize scene hashes file")
+}
+
+/// Records the current hashes (from the scenes cache) of the given scenes in a workspace.
+///
+/// Scenes that already have hashes recorded are only updated if `overwrite` is true, since their results were kept.
+pub fn record_scene_hashes(
+    group: &TechniqueType,
+    id: &str,
+    uuid: &str,
+    scenes: &HashSet<String>,
+    overwrite: bool,
+) {
+    let current: HashMap<String, SceneHashes> = read_scenes_cache()
+        .into_iter()
+        .flat_map(|r| r.scenes.into_iter())
+        .map(|s| (s.name, s.hashes))
+        .collect();
+    let mut hashes = read_scene_hashes(group, id, uuid);
+    for scene in scenes {
+        if !overwrite && hashes.contains_key(scene) {
+            continue;
+        }
+        if let Some(h) = current.get(scene) {
+            hashes.insert(scene.clone(), h.clone());
+        }
+    }
+    let data = serde_json::to_string_pretty(&hashes).expect("Failed to serialize scene hashes");
+    fs::write(paths::tech_scene_hashes_path(group, id, uuid), &data)
+        .expect("Failed to save scene hashes file");
+}
+
+/// A published technique's results for a scene.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct RerunEntry {
+    pub group: TechniqueType,
+    pub id: String,
+    pub scene: String,
+    /// Spps of the results (all spps if empty).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spps: Vec<i32>,
+}
+
+impl fmt::Display for RerunEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}: {}", self.group.as_str(), self.id, self.scene)?;
+        if !self.spps.is_empty() {
+            let spps: Vec<String> = self.spps.iter().map(|spp| spp.to_string()).collect();
+            write!(f, " ({} spp)", spps.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the published results against the current scenes hashes.
+///
+/// Returns the results computed with outdated versions of active scenes, and the ones with no recorded hashes (e.g.
+/// computed before hashes were recorded).
+pub fn outdated_results() -> (Vec<RerunEntry>, Vec<RerunEntry>) {
+    let current: HashMap<String, SceneHashes> = read_scenes_cache()
+        .into_iter()
+        .flat_map(|r| r.scenes.into_iter().filter(|s| !s.retired))
+        .map(|s| (s.name, s.hashes))
+        .collect();
+    let reg = reg::Registry::load();
+    let mut outdated = Vec::new();
+    let mut unknown = Vec::new();
+    for group in vec![TechniqueType::DENOISER, TechniqueType::SAMPLER] {
+        for (id, uuid) in reg.get_published(&group) {
+            let recorded = read_scene_hashes(&group, id, uuid);
+            let mut scenes: Vec<String> = result_scenes(&paths::tech_results_path(&group, id, uuid))
+                .into_iter()
+                .collect();
+            scenes.sort();
+            for scene in scenes {
+                let hashes = match current.get(&scene) {
+                    Some(hashes) => hashes,
+                    None => continue,
+                };
+                let entry = RerunEntry {
+                    group,
+                    id: id.clone(),
+                    scene: scene.clone(),
+                    spps: Vec::new(),
+                };
+                match recorded.get(&scene) {
+                    Some(h) if h == hashes => {}
+                    Some(_) => outdated.push(entry),
+                    None => unknown.push(entry),
+                }
+            }
+        }
+    }
+    (outdated, unknown)
+}
+
+/// Reads the queue of published results to run again.
+pub fn read_rerun_queue() -> Vec<RerunEntry> {
+    let path = paths::rerun_queue_path();
+    if !path.is_file() {
+        return Vec::new();
+    }
+    let data = fs::read_to_string(&path).expect("Failed to read re-run queue");
+    serde_json::from_str(&data).expect("Failed to deserialize re-run queue")
+}
+
+fn write_rerun_queue(queue: &[RerunEntry]) {
+    let data = serde_json::to_string_pretty(queue).expect("Failed to serialize re-run queue");
+    fs::write(paths::rerun_queue_path(), &data).expect("Failed to save re-run queue");
+}
+
+/// Adds entries to the re-run queue (ignoring the ones already queued).
+pub fn add_to_rerun_queue(entries: &[RerunEntry]) {
+    let mut queue = read_rerun_queue();
+    for e in entries {
+        if !queue.contains(e) {
+            queue.push(e.clone());
+        }
+    }
+    write_rerun_queue(&queue);
+}
+
+/// Removes entries from the re-run queue.
+pub fn remove_from_rerun_queue(entries: &[RerunEntry]) {
+    let mut queue = read_rerun_queue();
+    queue.retain(|e| !entries.contains(e));
+    write_rerun_queue(&queue);
+}
+
+/// Replaces the results of published techniques with the ones from the temporary workspace.
+///
+/// Only the given (technique, scene, spps) results are replaced, and only if the temporary workspace has results for
+/// all of them. Entries without spps replace all results of the scene (and its recorded hashes).
+/// Returns the entries that were replaced.
+pub fn replace_published_results(entries: &[RerunEntry]) -> Vec<RerunEntry> {
+    let tmp_workspace = paths::tmp_workspace_path();
+    let reg = reg::Registry::load();
+    let mut replaced = Vec::new();
+    for e in entries {
+        let uuid = match reg.get_published(&e.group).find(|p| p.0 == &e.id) {
+            Some((_, uuid)) => uuid.clone(),
+            None => continue,
+        };
+        let tech = match reg::Technique::read(paths::tech_install_path(&e.group, &e.id, &uuid).join("info.json")) {
+            Ok(tech) => tech,
+            Err(_) => continue,
+        };
+        let pattern = tmp_workspace
+            .join("results/.current")
+            .join(e.group.as_str())
+            .join(&tech.short_name)
+            .join("*")
+            .join(&e.scene);
+        let src = match glob(pattern.to_str().unwrap())
+            .expect("Failed to read glob pattern")
+            .filter_map(|p| p.ok())
+            .find(|p| p.is_dir())
+        {
+            Some(src) => src,
+            None => continue,
+        };
+        let version = src.parent().unwrap().file_name().unwrap();
+        let dest = paths::tech_results_path(&e.group, &e.id, &uuid).join(version);
+        let scene_dest = dest.join(&e.scene);
+        if e.spps.is_empty() {
+            if fs::read_dir(&src).map_or(true, |mut files| files.next().is_none()) {
+                continue;
+            }
+            if scene_dest.is_dir() {
+                fs::remove_dir_all(&scene_dest).expect("Failed to remove old results");
+            }
+            fs::create_dir_all(&dest).expect("Failed to create results folder");
+            let status = Command::new("rsync")
+                .args(&["-a", src.to_str().unwrap(), dest.join("").to_str().unwrap()])
+                .status();
+            if status.is_err() || !status.unwrap().success() {
+                std::process::exit(1);
+            }
+        } else if !replace_spp_results(&src, &scene_dest, &e.spps) {
+            continue;
+        }
+        // the scene hashes only apply if all its results were replaced
+        if e.spps.is_empty() {
+            let mut scenes = HashSet::new();
+            scenes.insert(e.scene.clone());
+            record_scene_hashes(&e.group, &e.id, &uuid, &scenes, true);
+        }
+        replaced.push(e.clone());
+    }
+    replaced
+}
+
+/// Replaces the result files of the given spps in a scene results folder with the ones from `src`.
+///
+/// Nothing is replaced (returns false) unless `src` has results for all the spps.
+fn replace_spp_results(src: &Path, scene_dest: &Path, spps: &[i32]) -> bool {
+    // result files: `<spp>_0.exr`, `<spp>_0_log.json`, `<spp>_0_<metric>_value.json`, ...
+    let spp_patterns = |spp: &i32| vec![format!("{}_0.*", spp), format!("{}_0_*", spp)];
+    let glob_files = |dir: &Path, patterns: &[String]| -> Vec<PathBuf> {
+        patterns
+            .iter()
+            .flat_map(|pattern| {
+                glob(dir.join(pattern).to_str().unwrap())
+                    .expect("Failed to read glob pattern")
+                    .filter_map(|p| p.ok())
+            })
+            .collect()
+    };
+    let mut new_files = Vec::new();
+    for spp in spps {
+        let files = glob_files(src, &spp_patterns(spp));
+        if files.is_empty() {
+            return false;
+        }
+        new_files.extend(files);
+    }
+    fs::create_dir_all(scene_dest).expect("Failed to create results folder");
+    let patterns: Vec<String> = spps.iter().flat_map(spp_patterns).collect();
+    for old in glob_files(scene_dest, &patterns) {
+        fs::remove_file(&old).expect("Failed to remove old result");
+    }
+    for new in new_files {
+        fs::copy(&new, scene_dest.join(new.file_name().unwrap())).expect("Failed to copy result");
+    }
+    true
+}
+
+/// A published result without the value of a metric.
+#[derive(Debug, Clone)]
+pub struct MissingMetric {
+    pub group: TechniqueType,
+    pub id: String,
+    pub scene: String,
+    pub spp: i32,
+    /// Metric acronym.
+    pub metric: String,
+    /// Scene results folder.
+    results: PathBuf,
+}
+
+impl fmt::Display for MissingMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} ({} spp) {}",
+            self.group.as_str(),
+            self.id,
+            self.scene,
+            self.spp,
+            self.metric
+        )
+    }
+}
+
+/// Finds the published results (not aborted and with an output image) missing the value of some metric.
+pub fn missing_metrics() -> Vec<MissingMetric> {
+    let metrics = read_metrics(paths::iqa_path());
+    let reg = reg::Registry::load();
+    let mut missing = Vec::new();
+    for group in vec![TechniqueType::DENOISER, TechniqueType::SAMPLER] {
+        let mut published: Vec<(&String, &String)> = reg.get_published(&group).collect();
+        published.sort();
+        for (id, uuid) in published {
+            let pattern = paths::tech_results_path(&group, id, uuid).join("*/*/*_0_log.json");
+            let mut logs: Vec<PathBuf> = glob(pattern.to_str().unwrap())
+                .expect("Failed to read glob pattern")
+                .filter_map(|p| p.ok())
+                .collect();
+            logs.sort();
+            for log in logs {
+                let results = log.parent().unwrap().to_path_buf();
+                let scene = results.file_name().unwrap().to_str().unwrap().to_string();
+                let res = Result::read(log, &scene);
+                if res.aborted || !results.join(format!("{}_0.exr", res.spp_budget)).is_file() {
+                    continue;
+                }
+                for m in &metrics {
+                    if !res.metrics.contains_key(&m.acronym) {
+                        missing.push(MissingMetric {
+                            group,
+                            id: id.clone(),
+                            scene: scene.clone(),
+                            spp: res.spp_budget,
+                            metric: m.acronym.clone(),
+                            results: results.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    missing
+}
+
+/// Returns the temporary workspace results folder of a missing metric value (see `create_tmp_metrics_workspace()`).
+fn tmp_metric_results(reg: &reg::Registry, m: &MissingMetric) -> Option<PathBuf> {
+    let (_, uuid) = reg.get_published(&m.group).find(|p| p.0 == &m.id)?;
+    let tech = reg::Technique::read(paths::tech_install_path(&m.group, &m.id, uuid).join("info.json")).ok()?;
+    let version = m.results.parent()?.file_name()?;
+    Some(
+        paths::tmp_workspace_path()
+            .join("results/.current")
+            .join(m.group.as_str())
+            .join(&tech.short_name)
+            .join(version)
+            .join(&m.scene),
+    )
+}
+
+/// Copies the output image and log of a result (without metric values) to a results folder.
+fn copy_result_files(src: &Path, dest: &Path, spp: i32) -> bool {
+    if fs::create_dir_all(dest).is_err() {
+        return false;
+    }
+    [format!("{}_0.exr", spp), format!("{}_0_log.json", spp)]
+        .iter()
+        .all(|file| fs::copy(src.join(file), dest.join(file)).is_ok())
+}
+
+/// Copies a metric value (and its error map, if any) of a result to a results folder.
+///
+/// Returns false if the value wasn't computed.
+fn copy_metric_files(src: &Path, dest: &Path, spp: i32, metric: &str) -> bool {
+    let value = format!("{}_0_{}_value.json", spp, metric);
+    if fs::copy(src.join(&value), dest.join(&value)).is_err() {
+        return false;
+    }
+    let map = format!("{}_0_{}_map.exr", spp, metric);
+    !src.join(&map).is_file() || fs::copy(src.join(&map), dest.join(&map)).is_ok()
+}
+
+/// Creates a temporary workspace to compute missing metric values with `fbksd results compute`.
+///
+/// The published techniques in `ids` are configured with the scenes (and spps) of their missing values, and their
+/// stored results are copied to the workspace (see `save_tmp_metrics()`).
+pub fn create_tmp_metrics_workspace(ids: &HashSet<String>, missing: &[MissingMetric]) -> WPResult<()> {
+    let missing: Vec<&MissingMetric> = missing.iter().filter(|m| ids.contains(&m.id)).collect();
+    let mut scenes: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+    for m in &missing {
+        let spps = scenes.entry(m.scene.clone()).or_default();
+        if !spps.contains(&m.spp) {
+            spps.push(m.spp);
+            spps.sort();
+        }
+    }
+    create_tmp_published_workspace(Some(ids), &scenes)?;
+    let reg = reg::Registry::load();
+    for m in missing {
+        match tmp_metric_results(&reg, m) {
+            Some(dest) if copy_result_files(&m.results, &dest, m.spp) => {}
+            _ => return Err(Error::Unspecified),
+        }
+    }
+    Ok(())
+}
+
+/// Saves the metric values computed in the temporary workspace next to the published results.
+///
+/// Returns the entries that have no computed value.
+pub fn save_tmp_metrics(missing: &[MissingMetric]) -> Vec<MissingMetric> {
+    let reg = reg::Registry::load();
+    missing
+        .iter()
+        .filter(|m| match tmp_metric_results(&reg, m) {
+            Some(src) => !copy_metric_files(&src, &m.results, m.spp, &m.metric),
+            None => true,
+        })
+        .cloned()
+        .collect()
 }
 
 /// Deletes a technique's unpublished workspace (including results page).
@@ -861,17 +2506,16 @@ pub fn trim_unpublished() {
 }
 
 /// Unpublishes a technique, setting its workspace as "Finished".
-pub fn unpublish_technique(id: i32) -> WPResult<()> {
-    let id = id.to_string();
+pub fn unpublish_technique(id: &str) -> WPResult<()> {
     let mut reg = reg::Registry::load();
-    if let Ok((group, uuid)) = reg.unpublish_workspace(&id) {
+    if let Ok((group, uuid)) = reg.unpublish_workspace(id) {
         // delete "published" link
-        if fs::remove_file(paths::tech_published_wp_path(&group, &id)).is_err() {
+        if fs::remove_file(paths::tech_published_wp_path(&group, id)).is_err() {
             return Err(Error::Unspecified);
         }
         // delete technique's results from the public page
         let tech = match reg::Technique::read(
-            paths::tech_install_path(&group, &id, uuid).join("info.json"),
+            paths::tech_install_path(&group, id, uuid).join("info.json"),
         ) {
             Ok(tech) => tech,
             _ => {
@@ -925,3 +2569,214 @@ pub fn set_public_page_permissions() -> WPResult<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_spp_results() {
+        let root = env::temp_dir().join("fbksd-test-replace-spp-results");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let src = root.join("src");
+        let dest = root.join("dest");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        for file in &["4_0.exr", "4_0_log.json", "8_0.exr", "8_0_log.json", "8_0_mse_value.json"] {
+            fs::write(dest.join(file), "old").unwrap();
+        }
+        fs::write(src.join("4_0.exr"), "new").unwrap();
+        fs::write(src.join("4_0_log.json"), "new").unwrap();
+
+        // no new results for 8 spp: nothing is replaced
+        assert!(!replace_spp_results(&src, &dest, &[4, 8]));
+        assert_eq!(fs::read_to_string(dest.join("4_0.exr")).unwrap(), "old");
+        assert!(dest.join("8_0_mse_value.json").is_file());
+
+        assert!(replace_spp_results(&src, &dest, &[4]));
+        assert_eq!(fs::read_to_string(dest.join("4_0.exr")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dest.join("4_0_log.json")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dest.join("8_0.exr")).unwrap(), "old");
+        assert!(dest.join("8_0_mse_value.json").is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_copy_metric_results() {
+        let root = env::temp_dir().join("fbksd-test-copy-metric-results");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let published = root.join("published");
+        let tmp = root.join("tmp");
+        fs::create_dir_all(&published).unwrap();
+        for file in &["4_0.exr", "4_0_log.json", "4_0_MSE_value.json", "8_0.exr"] {
+            fs::write(published.join(file), file).unwrap();
+        }
+
+        assert!(copy_result_files(&published, &tmp, 4));
+        assert!(tmp.join("4_0.exr").is_file() && tmp.join("4_0_log.json").is_file());
+        assert!(!tmp.join("4_0_MSE_value.json").exists());
+        // no log
+        assert!(!copy_result_files(&published, &tmp, 8));
+
+        // not computed
+        assert!(!copy_metric_files(&tmp, &published, 4, "SSIM"));
+        fs::write(tmp.join("4_0_SSIM_value.json"), "{\"SSIM\": 0.9}").unwrap();
+        fs::write(tmp.join("4_0_SSIM_map.exr"), "map").unwrap();
+        assert!(copy_metric_files(&tmp, &published, 4, "SSIM"));
+        assert!(published.join("4_0_SSIM_value.json").is_file());
+        assert!(published.join("4_0_SSIM_map.exr").is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn scene_hashes(renderers: &[Renderer]) -> BTreeMap<String, SceneHashes> {
+        renderers
+            .iter()
+            .flat_map(|r| r.scenes.iter().map(|s| (s.name.clone(), s.hashes.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_scenes_shared_folder() {
+        let root = env::temp_dir().join("fbksd-test-scan-shared-folder");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let dir = root.join("pbrt/shared");
+        fs::create_dir_all(dir.join("textures")).unwrap();
+        fs::write(
+            dir.join("fbksd-scenes.json"),
+            r#"[
+                {"name": "A", "path": "a.pbrt", "ref-img": "a.exr"},
+                {"name": "B", "path": "b.pbrt", "ref-img": "b.exr"}
+            ]"#,
+        )
+        .unwrap();
+        for file in &["a.pbrt", "a.exr", "b.pbrt", "b.exr", "textures/wood.txt"] {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        let (renderers, problems) = scan_scenes_dir(&root, true);
+        assert!(problems.iter().all(|p| p.severity == Severity::Warning));
+        let hashes = scene_hashes(&renderers);
+        assert!(!hashes["A"].scene.is_empty());
+        assert_ne!(hashes["A"].scene, hashes["B"].scene);
+
+        // changing a scene doesn't change the other scenes in the same folder
+        fs::write(dir.join("b.pbrt"), "changed").unwrap();
+        fs::write(dir.join("b.exr"), "changed").unwrap();
+        let new_hashes = scene_hashes(&scan_scenes_dir(&root, true).0);
+        assert_eq!(new_hashes["A"], hashes["A"]);
+        assert_ne!(new_hashes["B"].scene, hashes["B"].scene);
+        assert_ne!(new_hashes["B"].reference, hashes["B"].reference);
+
+        // shared files change all of them
+        fs::write(dir.join("textures/wood.txt"), "changed").unwrap();
+        let shared_hashes = scene_hashes(&scan_scenes_dir(&root, true).0);
+        assert_ne!(shared_hashes["A"].scene, new_hashes["A"].scene);
+        assert_ne!(shared_hashes["B"].scene, new_hashes["B"].scene);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn test_result(scene: &str, spp: i32, aborted: bool, mse: f32) -> Result {
+        Result {
+            aborted,
+            date: String::new(),
+            exec_time: ExecTime { time_ms: 0 },
+            spp_budget: spp,
+            id: 0,
+            metrics: vec![(String::from("MSE"), mse)].into_iter().collect(),
+            error_maps: HashMap::new(),
+            logs: HashMap::new(),
+            scene_name: String::from(scene),
+        }
+    }
+
+    fn test_technique(id: i32, results: Vec<Result>) -> Technique {
+        Technique {
+            short_name: id.to_string(),
+            full_name: String::new(),
+            comment: String::new(),
+            citation: String::new(),
+            results,
+            id,
+            private: false,
+        }
+    }
+
+    #[test]
+    fn test_common_results() {
+        let techs = vec![
+            test_technique(
+                1,
+                vec![
+                    test_result("A", 4, false, 1.0),
+                    test_result("B", 4, false, 3.0),
+                    test_result("C", 4, false, 5.0),
+                ],
+            ),
+            // B aborted and C missing
+            test_technique(2, vec![test_result("A", 4, false, 2.0), test_result("B", 4, true, 4.0)]),
+        ];
+        let common = common_results(&techs);
+        assert_eq!(common, vec![(String::from("A"), 4)].into_iter().collect());
+        assert_eq!(count_scenes(&common), vec![(4, 1)].into_iter().collect());
+        assert_eq!(techs[0].summary_over(&common)[&4]["MSE"], 1.0);
+        assert_eq!(techs[1].summary_over(&common)[&4]["MSE"], 2.0);
+        assert!(common_results(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_scan_scenes_problems() {
+        let root = env::temp_dir().join("fbksd-test-scan-problems");
+        if root.exists() {
+            fs::remove_dir_all(&root).unwrap();
+        }
+        let (renderers, problems) = scan_scenes_dir(&root, false);
+        assert!(renderers.is_empty());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].severity == Severity::Error);
+
+        fs::create_dir_all(root.join("pbrt/good")).unwrap();
+        fs::create_dir_all(root.join("pbrt/no-ref")).unwrap();
+        fs::create_dir_all(root.join("pbrt/broken")).unwrap();
+        fs::write(
+            root.join("pbrt/good/fbksd-scene.json"),
+            r#"{"name": "Good", "path": "scene.pbrt", "ref-img": "ref.exr", "tags": ["volumes"]}"#,
+        )
+        .unwrap();
+        fs::write(root.join("pbrt/good/scene.pbrt"), "").unwrap();
+        fs::write(root.join("pbrt/good/ref.exr"), "").unwrap();
+        fs::write(
+            root.join("pbrt/no-ref/fbksd-scene.json"),
+            r#"{"name": "NoRef", "path": "scene.pbrt", "ref-img": "ref.exr"}"#,
+        )
+        .unwrap();
+        fs::write(root.join("pbrt/no-ref/scene.pbrt"), "").unwrap();
+        fs::write(root.join("pbrt/broken/fbksd-scene.json"), r#"{"name": "Broken", "#).unwrap();
+
+        // dry-run: validated without hashing
+        let (renderers, problems) = scan_scenes_dir(&root, false);
+        let errors: Vec<&ScanProblem> = problems.iter().filter(|p| p.severity == Severity::Error).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|p| p.location == "pbrt/broken/fbksd-scene.json"));
+        assert!(errors
+            .iter()
+            .any(|p| p.location == "pbrt/no-ref/fbksd-scene.json" && p.message.contains("ref-img")));
+        // missing png and thumbnail of the good scene
+        assert!(problems
+            .iter()
+            .any(|p| p.severity == Severity::Warning && p.location == "pbrt/good/fbksd-scene.json"));
+        let hashes = scene_hashes(&renderers);
+        assert_eq!(hashes.keys().collect::<Vec<_>>(), ["Good", "NoRef"]);
+        assert_eq!(hashes["Good"], SceneHashes::default());
+
+        let (renderers, _) = scan_scenes_dir(&root, true);
+        let hashes = scene_hashes(&renderers);
+        assert!(!hashes["Good"].scene.is_empty());
+        assert_eq!(hashes["NoRef"], SceneHashes::default());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
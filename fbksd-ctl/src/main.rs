@@ -2,19 +2,25 @@
 //!
 //! This is a command line utility that performs administrative tasks in the server.
 
+use fbksd_core::config;
 use fbksd_core::docker;
+use fbksd_core::manifest;
 use fbksd_core::paths;
 use fbksd_core::registry as reg;
-use fbksd_core::utils::CD;
+use fbksd_core::report;
+use fbksd_core::runtime::{self, Outcome};
+use fbksd_core::suites;
+use fbksd_core::system_config::{ParallelConfig, SystemConfig};
 use fbksd_core::utils::*;
 use fbksd_core::{try_flock};
 use fbksd_core::workspace as wp;
 use wp::Workspace;
 
 use clap::{load_yaml, App};
+use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::path::Path;
+use std::thread;
 
 fn status() {
     if !Path::new(paths::LOCK_FILE).exists() {
@@ -27,44 +33,318 @@ fn status() {
     }
 }
 
-fn run_all() {
-    //TODO: techniques can require different docker images.
+/// Runs `fbksd run` in a workspace with the configured runtime (using the given image).
+///
+/// If the run reaches the timeout, the results it didn't finish are saved as aborted.
+fn run_step(image: &str, workspace: &Path) {
+    let runtime = runtime::from_config();
+    if let Outcome::TimedOut = runtime.run(image, workspace, "fbksd", &["run"]).unwrap() {
+        let count = wp::abort_missing_results(workspace);
+        eprintln!("[{}] run timed out: {} results aborted", image, count);
+    }
+}
+
+/// Runs `fbksd results compute` in a workspace with the configured runtime (using the given image).
+fn compute_step(image: &str, workspace: &Path) {
+    let runtime = runtime::from_config();
+    if let Outcome::TimedOut = runtime
+        .run(image, workspace, "fbksd", &["results", "compute"])
+        .unwrap()
+    {
+        eprintln!("[{}] results computation timed out", image);
+    }
+}
+
+/// Runs a step in the temporary workspace.
+///
+/// The work is split into shards that run in parallel, according to the parallel configuration.
+fn run_sharded(image: &str, parallel: &ParallelConfig, step: fn(&str, &Path)) {
+    let shards = wp::create_tmp_shards(parallel.workers, parallel.shard_by);
+    if shards.is_empty() {
+        step(image, paths::tmp_workspace_path());
+        return;
+    }
+    println!("[{}] running {} shards in parallel...", image, shards.len());
+    let handles: Vec<_> = shards
+        .iter()
+        .cloned()
+        .map(|shard| {
+            let image = String::from(image);
+            thread::spawn(move || step(&image, &shard))
+        })
+        .collect();
+    for handle in handles {
+        handle.join().expect("Shard run failed");
+    }
+    wp::merge_tmp_shards(&shards);
+}
+
+/// Records the digest of the image the temporary workspace is about to run with (see `manifest::record_run_start()`).
+fn record_run_start(image: &str) {
+    manifest::record_run_start(paths::tmp_workspace_path(), docker::image_digest(image));
+}
+
+/// Runs the benchmark in the temporary workspace.
+///
+/// `fbksd run` runs with each of the given configs, and the results are computed with the main config.
+fn run_benchmark(image: &str, parallel: &ParallelConfig, configs: &[String]) {
+    for c in configs {
+        wp::set_tmp_config(c);
+        run_sharded(image, parallel, run_step);
+    }
+    wp::set_tmp_config(config::MAIN_CONFIG);
+    run_sharded(image, parallel, compute_step);
+}
+
+/// Runs all published techniques and updates their results.
+///
+/// Techniques are grouped by the docker image they were built with, and each group runs in its own container.
+/// The progress is recorded in a checkpoint, so an interrupted run-all is resumed (running only the results
+/// without logs) unless `fresh` is true.
+/// With `dry_run`, only the plan of what would run is printed.
+fn run_all(suite: Option<&str>, parallel: &ParallelConfig, fresh: bool, dry_run: bool) {
+    let suite = suite.map(|id| match suites::find(id) {
+        Some(suite) => suite,
+        None => {
+            eprintln!("suite {} not found", id);
+            std::process::exit(1);
+        }
+    });
+    if dry_run {
+        let plans = wp::plan_run_all(suite.as_ref());
+        for plan in &plans {
+            print!("{}", plan);
+        }
+        let total: i64 = plans.iter().map(|p| p.estimate_ms()).sum();
+        println!("total estimated time: {}", wp::format_duration(total));
+        if parallel.workers > 1 {
+            println!(
+                "with {} workers: about {}",
+                parallel.workers,
+                wp::format_duration(total / parallel.workers as i64)
+            );
+        }
+        return;
+    }
+    let _lock = FLock::new();
+    let suite_id = suite.as_ref().map(|s| s.id());
+    let mut checkpoint = match wp::Checkpoint::load() {
+        Some(checkpoint) if !fresh && checkpoint.suite == suite_id => {
+            println!("resuming interrupted run (use --fresh to start over)");
+            checkpoint
+        }
+        Some(_) if !fresh => {
+            eprintln!("an interrupted run with another suite exists (use --fresh to start over)");
+            std::process::exit(1);
+        }
+        _ => wp::Checkpoint {
+            suite: suite_id,
+            ..Default::default()
+        },
+    };
+    for (image, ids) in wp::published_by_image() {
+        if checkpoint.completed.contains(&image) {
+            println!("[{}] already completed", image);
+            continue;
+        }
+        let resume = checkpoint.current.as_ref() == Some(&image) && paths::tmp_workspace_path().is_dir();
+        let configs = if resume {
+            println!("[{}] resuming temporary workspace...", image);
+            wp::write_resume_configs()
+        } else {
+            println!("[{}] building temporary workspace...", image);
+            wp::create_tmp_workspace(Some(&ids), suite.as_ref());
+            record_run_start(&image);
+            checkpoint.current = Some(image.clone());
+            checkpoint.save();
+            vec![String::from(config::MAIN_CONFIG)]
+        };
+        println!(" - OK");
+
+        run_benchmark(&image, parallel, &configs);
+
+        println!("[{}] saving results...", image);
+        wp::save_tmp_workspace(false, false);
+        checkpoint.current = None;
+        checkpoint.completed.push(image);
+        checkpoint.save();
+        println!(" - OK");
+    }
+
+    println!("recording history...");
+    record_history();
+    println!(" - OK");
+
+    println!("updating page...");
+    update_page();
+    wp::Checkpoint::remove();
+    println!(" - OK");
+}
+
+/// Runs all published techniques with the scenes added since they last ran.
+fn run_new_scenes() {
+    let _lock = FLock::new();
+    let scenes = wp::read_pending_scenes();
+    if scenes.is_empty() {
+        println!("no new scenes to run");
+        return;
+    }
+    println!("new scenes: {}", scenes.join(", "));
+    let scenes_spps = wp::select_scenes(&scenes, None, &[]);
+    for (image, ids) in wp::published_by_image() {
+        println!("[{}] building temporary workspace...", image);
+        wp::create_tmp_published_workspace(Some(&ids), &scenes_spps)
+            .expect("Failed to create temporary workspace");
+        record_run_start(&image);
+        println!(" - OK");
+
+        run_benchmark(
+            &image,
+            &SystemConfig::load().parallel,
+            &[String::from(config::MAIN_CONFIG)],
+        );
+
+        println!("[{}] saving results...", image);
+        wp::save_tmp_workspace(false, false);
+        println!(" - OK");
+    }
+    wp::remove_pending_scenes(&scenes);
+
+    println!("recording history...");
+    record_history();
+    println!(" - OK");
+
+    println!("updating page...");
+    update_page();
+    println!(" - OK");
+}
+
+/// Lists the published results computed with outdated scenes, optionally queuing them to run again.
+fn check_scenes(queue: bool) {
+    let (outdated, unknown) = wp::outdated_results();
+    for e in &outdated {
+        println!("outdated: {}", e);
+    }
+    if !unknown.is_empty() {
+        println!("{} results without recorded scene hashes", unknown.len());
+    }
+    if outdated.is_empty() {
+        println!("no outdated results");
+        return;
+    }
+    if queue {
+        try_flock!(
+            {
+                wp::add_to_rerun_queue(&outdated);
+                println!("{} results queued to run again", outdated.len());
+            },
+            println!("not queued: being used by other process")
+        );
+    }
+}
+
+/// Runs the given published results again, replacing the old ones.
+///
+/// Returns the entries whose results were replaced.
+fn rerun_results(entries: &[wp::RerunEntry]) -> Vec<wp::RerunEntry> {
+    let all_spps = wp::select_scenes(&[], None, &[]);
+    let entries_ids: HashSet<String> = entries.iter().map(|e| e.id.clone()).collect();
+    let mut replaced = Vec::new();
+    for (image, ids) in wp::published_by_image() {
+        let ids: HashSet<String> = ids.intersection(&entries_ids).cloned().collect();
+        if ids.is_empty() {
+            continue;
+        }
+        let entries: Vec<wp::RerunEntry> = entries.iter().filter(|e| ids.contains(&e.id)).cloned().collect();
+        let mut scenes: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        for e in &entries {
+            let spps = if e.spps.is_empty() {
+                all_spps.get(&e.scene).cloned().unwrap_or_default()
+            } else {
+                e.spps.clone()
+            };
+            let scene_spps = scenes.entry(e.scene.clone()).or_insert_with(Vec::new);
+            scene_spps.extend(spps);
+            scene_spps.sort();
+            scene_spps.dedup();
+        }
+        println!("[{}] building temporary workspace...", image);
+        wp::create_tmp_published_workspace(Some(&ids), &scenes)
+            .expect("Failed to create temporary workspace");
+        record_run_start(&image);
+        println!(" - OK");
+
+        run_benchmark(
+            &image,
+            &SystemConfig::load().parallel,
+            &[String::from(config::MAIN_CONFIG)],
+        );
+
+        println!("[{}] saving results...", image);
+        replaced.extend(wp::replace_published_results(&entries));
+        wp::save_tmp_workspace(false, false);
+        println!(" - OK");
+    }
+    replaced
+}
+
+/// Runs the published results in the re-run queue again, replacing the old ones.
+fn run_queue() {
     let _lock = FLock::new();
-    println!("building temporary workspace...");
-    wp::create_tmp_workspace(true);
+    let queue = wp::read_rerun_queue();
+    if queue.is_empty() {
+        println!("re-run queue is empty");
+        return;
+    }
+    let replaced = rerun_results(&queue);
+    wp::remove_from_rerun_queue(&replaced);
+    println!("{} of {} results replaced", replaced.len(), queue.len());
+
+    println!("recording history...");
+    record_history();
     println!(" - OK");
 
-    let tmp_workspace = paths::tmp_workspace_path();
-    let _cd = CD::new(&tmp_workspace);
-    docker::run("fbksd", &["run"]).unwrap();
-    docker::run("fbksd", &["results", "compute"]).unwrap();
+    println!("updating page...");
+    update_page();
+    println!(" - OK");
+}
 
-    println!("saving results...");
+/// Runs the published results matching the filters again, replacing the old ones.
+///
+/// Empty filters match everything.
+fn rerun(ids: &[String], scenes: &[String], spps: &[i32], renderer: Option<&str>) {
+    let _lock = FLock::new();
+    let selected = wp::select_scenes(scenes, renderer, spps);
     let reg = reg::Registry::load();
+    let mut entries = Vec::new();
     for group in vec![reg::TechniqueType::DENOISER, reg::TechniqueType::SAMPLER] {
-        let published = reg.get_published(&group);
-        for p in published {
-            let base = paths::tech_workspace_path(&group, &p.0, &p.1);
-            let tech =
-                reg::Technique::read(base.join(paths::TECH_INSTALL_DIR).join("info.json")).unwrap();
-            let src = PathBuf::from("results/.current")
-                .join(group.as_str())
-                .join(&tech.short_name)
-                .join("");
-            let dest = base.join("results/");
-            let status = Command::new("rsync")
-                .args(&[
-                    "-a",
-                    "--ignore-existing",
-                    src.to_str().unwrap(),
-                    dest.to_str().unwrap(),
-                ])
-                .status();
-            if status.is_err() || !status.unwrap().success() {
-                std::process::exit(1);
+        for (id, _) in reg.get_published(&group) {
+            if !ids.is_empty() && !ids.contains(id) {
+                continue;
+            }
+            for (scene, scene_spps) in &selected {
+                // whole scenes are replaced (with their hashes) when the spps are not filtered
+                entries.push(wp::RerunEntry {
+                    group,
+                    id: id.clone(),
+                    scene: scene.clone(),
+                    spps: if spps.is_empty() { Vec::new() } else { scene_spps.clone() },
+                });
             }
         }
     }
+    if entries.is_empty() {
+        eprintln!("no published results match the filters");
+        std::process::exit(1);
+    }
+    for e in &entries {
+        println!("re-run: {}", e);
+    }
+    let replaced = rerun_results(&entries);
+    println!("{} of {} results replaced", replaced.len(), entries.len());
+
+    println!("recording history...");
+    record_history();
     println!(" - OK");
 
     println!("updating page...");
@@ -72,6 +352,71 @@ fn run_all() {
     println!(" - OK");
 }
 
+/// Computes the metric values missing from the published results, without running the techniques again.
+///
+/// The stored results are copied to a temporary workspace, where `fbksd results compute` runs like in `run_all()`.
+fn compute_metrics(dry_run: bool) {
+    let _lock = FLock::new();
+    let missing = wp::missing_metrics();
+    if missing.is_empty() {
+        println!("no missing metric values");
+        return;
+    }
+    for m in &missing {
+        println!("missing: {}", m);
+    }
+    if dry_run {
+        return;
+    }
+    let parallel = SystemConfig::load().parallel;
+    let missing_ids: HashSet<String> = missing.iter().map(|m| m.id.clone()).collect();
+    let mut failed = Vec::new();
+    for (image, ids) in wp::published_by_image() {
+        let ids: HashSet<String> = ids.intersection(&missing_ids).cloned().collect();
+        if ids.is_empty() {
+            continue;
+        }
+        println!("[{}] building temporary workspace...", image);
+        wp::create_tmp_metrics_workspace(&ids, &missing).expect("Failed to create temporary workspace");
+        println!(" - OK");
+
+        run_sharded(&image, &parallel, compute_step);
+
+        println!("[{}] saving metric values...", image);
+        let entries: Vec<wp::MissingMetric> = missing.iter().filter(|m| ids.contains(&m.id)).cloned().collect();
+        failed.extend(wp::save_tmp_metrics(&entries));
+        println!(" - OK");
+    }
+    for m in &failed {
+        eprintln!("failed: {}", m);
+    }
+    println!("{} of {} metric values computed", missing.len() - failed.len(), missing.len());
+
+    println!("recording history...");
+    record_history();
+    println!(" - OK");
+
+    println!("updating page...");
+    update_page();
+    println!(" - OK");
+}
+
+/// Records the current results of all published techniques in their history (skipping the unchanged ones).
+fn record_history() {
+    let mut reg = reg::Registry::load();
+    for group in vec![reg::TechniqueType::DENOISER, reg::TechniqueType::SAMPLER] {
+        let published: Vec<(String, String)> = reg
+            .get_published(&group)
+            .map(|(id, uuid)| (id.clone(), uuid.clone()))
+            .collect();
+        for (id, uuid) in published {
+            wp::record_history(&mut reg, &group, &id, &uuid, reg::HistoryEvent::ResultsUpdated)
+                .expect("Failed to record history");
+        }
+    }
+    reg.save();
+}
+
 fn update_page() {
     let public_page = paths::public_page_path();
     let wp = Workspace::load();
@@ -81,13 +426,15 @@ fn update_page() {
     wp::set_public_page_permissions().unwrap();
 }
 
-fn unpublish(id: i32) {
+fn unpublish(id: &str) {
     wp::unpublish_technique(id).expect("Failed to unpublish.");
 }
 
-fn update_scenes() {
+fn update_scenes(dry_run: bool) {
     try_flock!(
-        wp::update_scenes(),
+        if wp::update_scenes(dry_run).is_err() {
+            std::process::exit(1);
+        },
         println!("not updated: being used by other process")
     );
 }
@@ -96,15 +443,71 @@ fn trim() {
     try_flock!(wp::trim_unpublished(), println!("failed to acquire lock"));
 }
 
+/// Creates a temporary workspace from a workspace manifest, reporting what differs from when it was produced.
+fn reproduce(path: &str, index: Option<usize>) {
+    let manifests = manifest::read(Path::new(path));
+    let index = index.unwrap_or_else(|| manifests.len().saturating_sub(1));
+    let m = match manifests.get(index) {
+        Some(m) => m,
+        None => {
+            eprintln!("manifest {} not found in {}", index, path);
+            std::process::exit(1);
+        }
+    };
+    let _lock = FLock::new();
+    if let Err(err) = wp::create_tmp_manifest_workspace(m) {
+        eprintln!("failed to create workspace for {}/{}: {}", m.id, m.uuid, err);
+        std::process::exit(1);
+    }
+    for diff in m.differences() {
+        println!("warning: {}", diff);
+    }
+    println!("workspace created in {}", paths::tmp_workspace_path().display());
+    match &m.docker_image_digest {
+        Some(digest) => println!("image: {} ({})", m.docker_image, digest),
+        None => println!("image: {}", m.docker_image),
+    }
+}
+
+fn report(id: &str, uuid: &str, format: report::Format, output: Option<&str>) {
+    let group = match reg::Registry::load().technique_type(id) {
+        Some(group) => group,
+        None => {
+            eprintln!("technique {} is not registered", id);
+            std::process::exit(1);
+        }
+    };
+    let report = wp::generate_report(&group, id, uuid, format).expect("Failed to generate report");
+    match output {
+        Some(file) => std::fs::write(file, &report).expect("Failed to save report"),
+        None => print!("{}", report),
+    }
+}
+
 fn main() {
     let yaml = load_yaml!("cli.yml");
     let matches = App::from_yaml(yaml).get_matches();
     match matches.subcommand() {
         ("status", Some(_)) => status(),
-        ("run-all", Some(_)) => run_all(),
+        ("run-all", Some(sub)) => {
+            let mut parallel = SystemConfig::load().parallel;
+            if let Some(workers) = sub.value_of("workers") {
+                parallel.workers = workers.parse().expect("invalid number of workers");
+            }
+            if let Some(by) = sub.value_of("shard-by") {
+                parallel.shard_by = by.parse().unwrap();
+            }
+            run_all(
+                sub.value_of("suite"),
+                &parallel,
+                sub.is_present("fresh"),
+                sub.is_present("dry-run"),
+            );
+        }
+        ("run-new-scenes", Some(_)) => run_new_scenes(),
         ("unpublish", Some(sub)) => {
-            let id: i32 = match sub.value_of("id") {
-                Some(id) => id.parse().unwrap(),
+            let id = match sub.value_of("id") {
+                Some(id) => id,
                 None => {
                     eprintln!("missing id argument");
                     std::process::exit(1);
@@ -113,8 +516,33 @@ fn main() {
             unpublish(id);
         }
         ("update-page", Some(_)) => update_page(),
-        ("update-scenes", Some(_)) => update_scenes(),
+        ("update-scenes", Some(sub)) => update_scenes(sub.is_present("dry-run")),
+        ("check-scenes", Some(sub)) => check_scenes(sub.is_present("queue")),
+        ("run-queue", Some(_)) => run_queue(),
+        ("compute-metrics", Some(sub)) => compute_metrics(sub.is_present("dry-run")),
+        ("rerun", Some(sub)) => {
+            let values = |name| -> Vec<String> {
+                sub.values_of(name)
+                    .map(|v| v.map(String::from).collect())
+                    .unwrap_or_default()
+            };
+            let spps: Vec<i32> = values("spp")
+                .iter()
+                .map(|spp| spp.parse().expect("invalid spp"))
+                .collect();
+            rerun(&values("id"), &values("scene"), &spps, sub.value_of("renderer"));
+        }
+        ("reproduce", Some(sub)) => reproduce(
+            sub.value_of("manifest").unwrap(),
+            sub.value_of("index").map(|i| i.parse().expect("invalid index")),
+        ),
         ("trim", Some(_)) => trim(),
+        ("report", Some(sub)) => report(
+            sub.value_of("id").unwrap(),
+            sub.value_of("uuid").unwrap(),
+            sub.value_of("format").unwrap().parse().unwrap(),
+            sub.value_of("output"),
+        ),
         _ => println!("No subcommand was used"),
     }
 }
@@ -5,12 +5,15 @@
 //! This separation prevents the fbksd-ci program (which handles untrusted code) from having direct access to the data.
 
 use fbksd_core;
-use fbksd_core::ci::ProjectInfo;
+use fbksd_core::ci::{self, ProjectInfo};
+use fbksd_core::docker;
+use fbksd_core::manifest::{self, Manifest};
 use fbksd_core::system_config::SystemConfig;
 use fbksd_core::msgs::{Error, Msg, MsgResult};
 use fbksd_core::page;
 use fbksd_core::paths;
 use fbksd_core::registry as reg;
+use fbksd_core::report;
 use fbksd_core::workspace as wp;
 use reg::{Registry, Technique};
 use wp::Workspace;
@@ -91,7 +94,16 @@ fn save_results(proj: ProjectInfo, tech: Technique) -> MsgResult {
             Err(e) => log::error!("{:?}", e),
         }
     }
+    let scenes = wp::result_scenes(&base.join(paths::TECH_RESULTS_DIR));
+    wp::record_scene_hashes(&group, &proj.id, &uuid, &scenes, true);
     registry.save();
+    manifest::record(Manifest::new(
+        &group,
+        &proj.id,
+        &uuid,
+        &tech.short_name,
+        paths::tmp_workspace_path(),
+    ));
     log::info!("results saved in private folder");
     Ok(uuid)
 }
@@ -125,6 +137,9 @@ fn publish_private(proj: ProjectInfo, uuid: String) -> MsgResult {
     if !wp::export_technique_images(&src, &dest, false) {
         return Err(Error::Unspecified);
     }
+    if !wp::export_technique_logs(&src, &dest) {
+        return Err(Error::Unspecified);
+    }
     let mut reg = reg::Registry::load();
     if reg.publish_workspace_private(&proj, &uuid).is_err() {
         return Err(Error::Unspecified);
@@ -143,9 +158,11 @@ fn init_missing_scenes_workspace(proj: ProjectInfo, uuid: String) -> MsgResult {
         &uuid
     );
     let group = reg::Registry::load().technique_type(&proj.id).unwrap();
+    let digest = docker::image_digest(&proj.docker_img);
     match wp::create_tmp_technique_workspace(&group, proj, &uuid) {
         Ok(has_scenes) => {
             if has_scenes {
+                manifest::record_run_start(paths::tmp_workspace_path(), digest);
                 Ok(String::new())
             } else {
                 Ok(String::from("NO_SCENE"))
@@ -192,13 +209,25 @@ fn publish_public(info: ProjectInfo, uuid: String) -> MsgResult {
         _ => return Err(Error::Unspecified),
     }
 
+    let group = registry.technique_type(&info.id).unwrap();
+    if wp::record_history(
+        &mut registry,
+        &group,
+        &info.id,
+        &uuid,
+        reg::HistoryEvent::Published,
+    )
+    .is_err()
+    {
+        log::warn!("failed to record publication history");
+    }
+    registry.save();
+
     let public_page = paths::public_page_path();
     let private_page = public_page.join(&uuid);
     let mut wp = Workspace::load();
-    let group = reg::Registry::load().technique_type(&info.id).unwrap();
     wp.load_technique(&group, &info, uuid.to_string());
     wp.export_page(&public_page);
-    registry.save();
 
     // create link to published data
     let base = paths::tech_workspace_path(&group, &info.id, &uuid);
@@ -232,6 +261,8 @@ fn publish_public(info: ProjectInfo, uuid: String) -> MsgResult {
             return Err(Error::Unspecified);
         }
     }
+    // run logs are only shown in the private page
+    wp::remove_technique_logs(&src);
     let status = Command::new("mv")
         .args(&[src.to_str().unwrap(), dest.to_str().unwrap()])
         .stdout(Stdio::null())
@@ -248,6 +279,9 @@ fn publish_public(info: ProjectInfo, uuid: String) -> MsgResult {
     Ok(String::from("Published."))
 }
 
+/// Checks if a technique can run, returning the digest of its docker image (empty if it can't be inspected).
+///
+/// The digest is recorded in the manifest of the run results (see `manifest::record_run_start()`).
 fn can_run(info: ProjectInfo) -> MsgResult {
     log::info!("can run: id = {}", &info.id);
     let num = reg::Registry::load().get_unpublished_wps(&info.id).count();
@@ -257,7 +291,7 @@ fn can_run(info: ProjectInfo) -> MsgResult {
         return Err(Error::MaxWorkspacesExceeded);
     }
     log::info!("can run: num({}) < max({})", num, max);
-    Ok(num.to_string())
+    Ok(docker::image_digest(&info.docker_img).unwrap_or_default())
 }
 
 fn delete_workspace(info: ProjectInfo, uuid: String) -> MsgResult {
@@ -268,6 +302,36 @@ fn delete_workspace(info: ProjectInfo, uuid: String) -> MsgResult {
     Err(Error::Unspecified)
 }
 
+/// Generates the comparison reports (html and markdown) in the workspace's private page.
+fn generate_report(info: ProjectInfo, uuid: String) -> MsgResult {
+    log::info!("generate report: id = {}, uuid = {}", &info.id, &uuid);
+    let registry = Registry::load();
+    let group = match registry.technique_type(&info.id) {
+        Some(group) => group,
+        None => return Err(Error::Unspecified),
+    };
+    // the report is written in the private page, so the uuid must be an unpublished workspace of the technique
+    if !registry.get_unpublished_wps(&info.id).any(|u| *u == uuid) {
+        log::warn!("generate report: uuid {} is not an unpublished workspace of {}", &uuid, &info.id);
+        return Err(Error::Unspecified);
+    }
+    let private_page = paths::public_page_path().join(&uuid);
+    if !private_page.is_dir() {
+        return Err(Error::Unspecified);
+    }
+    for format in vec![report::Format::Html, report::Format::Markdown] {
+        let data = match wp::generate_report(&group, &info.id, &uuid, format) {
+            Ok(data) => data,
+            Err(_) => return Err(Error::Unspecified),
+        };
+        fs::write(private_page.join(format!("report.{}", format.extension())), &data)?;
+    }
+    if wp::set_public_page_permissions().is_err() {
+        return Err(Error::Unspecified);
+    }
+    Ok(String::from("report"))
+}
+
 fn main() {
     // config logger
     let stdout = ConsoleAppender::builder().build();
@@ -292,7 +356,11 @@ fn main() {
                 log::info!("new connection");
                 loop {
                     let mut de = serde_json::Deserializer::from_reader(&stream);
-                    let msg = Msg::deserialize(&mut de).unwrap_or(Msg::Invalid);
+                    let mut msg = Msg::deserialize(&mut de).unwrap_or(Msg::Invalid);
+                    // project ids are used in paths
+                    if msg.project().is_some_and(|info| !ci::is_valid_project_id(&info.id)) {
+                        msg = Msg::Invalid;
+                    }
                     let res = match msg {
                         Msg::Register(info, tech) => register(info, tech),
                         Msg::SaveResults(info, tech) => save_results(info, tech),
@@ -304,6 +372,7 @@ fn main() {
                         Msg::PublishPublic(info, uuid) => publish_public(info, uuid),
                         Msg::CanRun(info) => can_run(info),
                         Msg::DeleteWorkspace(info, uuid) => delete_workspace(info, uuid),
+                        Msg::GenerateReport(info, uuid) => generate_report(info, uuid),
                         Msg::End => {
                             log::info!("connection ended by client");
                             break;